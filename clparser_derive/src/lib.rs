@@ -0,0 +1,444 @@
+//! `#[derive(CLPArgs)]` for `clparser`, see the `CLPArgs` trait for the
+//! attributes it takes.
+
+use std::collections::HashSet;
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Expr, ExprLit, Fields, GenericArgument, Ident, Lit, LitChar, LitStr, Meta, PathArguments, Token, Type};
+
+#[proc_macro_derive(CLPArgs, attributes(arg))]
+pub fn derive_clp_args(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+enum Kind {
+    Positional,
+    Kwarg,
+    Toggle,
+    Rest
+}
+
+struct Field {
+    ident: Ident,
+    ty: Type,
+    kind: Kind,
+    name: String,
+    keys: Vec<String>,
+    arg_type: Option<TokenStream2>,
+    constraints: Vec<Expr>,
+    default: Option<Expr>,
+    help: String,
+    value_name: String,
+    error: String
+}
+
+/// The doc comment of an item joined into one line.
+fn doc(attrs: &[Attribute]) -> String {
+    attrs.iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(ExprLit { lit: Lit::Str(line), .. }) => Some(line.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The type argument of `wrapper<T>`, as in `Option<T>` or `Vec<T>`.
+fn unwrap<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let segment = match ty {
+        Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != wrapper {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn scalar(ty: &Type) -> Option<&'static str> {
+    let ident = match ty {
+        Type::Path(path) => path.path.segments.last()?.ident.to_string(),
+        _ => return None,
+    };
+    match ident.as_str() {
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" => Some("INT"),
+        "u8" | "u16" | "u32" | "u64" | "u128" | "usize" => Some("UINT"),
+        "f32" | "f64" => Some("FLOAT"),
+        "String" => Some("STRING"),
+        "PathBuf" => Some("PATH"),
+        "OsString" => Some("OSSTRING"),
+        "Duration" => Some("DURATION"),
+        "IpAddr" => Some("IPADDR"),
+        "SocketAddr" => Some("SOCKETADDR"),
+        "CLPDate" => Some("DATE"),
+        "CLPDateTime" => Some("DATETIME"),
+        _ => None,
+    }
+}
+
+/// The `ArgTypes` variant for a field type, `Option` is looked through.
+fn infer(ty: &Type) -> Option<&'static str> {
+    let ty = unwrap(ty, "Option").unwrap_or(ty);
+    let element = match unwrap(ty, "Vec") {
+        Some(element) => element,
+        None => return scalar(ty),
+    };
+    if let Some(element) = unwrap(element, "Vec") {
+        return match scalar(element)? {
+            "INT" => Some("VECVECINT"),
+            "UINT" => Some("VECVECUINT"),
+            "FLOAT" => Some("VECVECFLOAT"),
+            _ => None,
+        };
+    }
+    match scalar(element)? {
+        "INT" => Some("VECINT"),
+        "UINT" => Some("VECUINT"),
+        "FLOAT" => Some("VECFLOAT"),
+        "STRING" | "PATH" => Some("VECSTRING"),
+        "IPADDR" => Some("VECIPADDR"),
+        "SOCKETADDR" => Some("VECSOCKETADDR"),
+        _ => None,
+    }
+}
+
+fn parse_field(field: &syn::Field) -> syn::Result<Field> {
+    let ident = match &field.ident {
+        Some(ident) => ident.clone(),
+        None => return Err(syn::Error::new_spanned(field, "CLPArgs needs named fields")),
+    };
+    let field_name = ident.to_string().trim_start_matches("r#").to_string();
+    let mut long = None;
+    let mut short = None;
+    let mut aliases = Vec::new();
+    let mut name = None;
+    let mut arg_type = None;
+    let mut constraints = Vec::new();
+    let mut default = None;
+    let mut help = None;
+    let mut value_name = None;
+    let mut error = String::new();
+    let mut count = false;
+    let mut rest = false;
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("arg")) {
+        attr.parse_nested_meta(|meta| {
+            let path = &meta.path;
+            if path.is_ident("long") {
+                long = Some(match meta.input.peek(Token![=]) {
+                    true => meta.value()?.parse::<LitStr>()?.value().trim_start_matches('-').to_string(),
+                    false => field_name.replace('_', "-"),
+                });
+            } else if path.is_ident("short") {
+                short = Some(match meta.input.peek(Token![=]) {
+                    true => meta.value()?.parse::<LitChar>()?.value(),
+                    false => field_name.chars().next().unwrap_or('_'),
+                });
+            } else if path.is_ident("alias") {
+                let alias = meta.value()?.parse::<LitStr>()?.value();
+                aliases.push(match alias.starts_with('-') {
+                    true => alias,
+                    false if alias.chars().count() == 1 => format!("-{}", alias),
+                    false => format!("--{}", alias),
+                });
+            } else if path.is_ident("name") {
+                name = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if path.is_ident("ty") {
+                let ty: Expr = meta.value()?.parse()?;
+                arg_type = Some(quote! {{
+                    #[allow(unused_imports)]
+                    use ::clparser::ArgTypes::*;
+                    #ty
+                }});
+            } else if path.is_ident("constraint") {
+                constraints.push(meta.value()?.parse()?);
+            } else if path.is_ident("default") {
+                default = Some(meta.value()?.parse()?);
+            } else if path.is_ident("help") {
+                help = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if path.is_ident("value_name") {
+                value_name = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if path.is_ident("error") {
+                error = meta.value()?.parse::<LitStr>()?.value();
+            } else if path.is_ident("count") {
+                count = true;
+            } else if path.is_ident("rest") {
+                rest = true;
+            } else {
+                return Err(meta.error("unknown arg attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    let toggle = count || matches!(&field.ty, Type::Path(path) if path.path.is_ident("bool"));
+    if toggle && long.is_none() && short.is_none() && aliases.is_empty() {
+        long = Some(field_name.replace('_', "-"));
+    }
+    let mut keys: Vec<String> = short.map(|short| format!("-{}", short)).into_iter().collect();
+    keys.extend(long.map(|long| format!("--{}", long)));
+    keys.extend(aliases);
+    let kind = match (rest, toggle, keys.is_empty()) {
+        (true, _, _) => Kind::Rest,
+        (false, true, _) => Kind::Toggle,
+        (false, false, true) => Kind::Positional,
+        (false, false, false) => Kind::Kwarg,
+    };
+    // the parser needs every positional before the first option
+    if let Kind::Positional = kind {
+        if unwrap(&field.ty, "Option").is_some() || default.is_some() {
+            return Err(syn::Error::new_spanned(&ident, "positionals are required, add `long` or `short` for an optional value"));
+        }
+    }
+    let arg_type = match (arg_type, &kind) {
+        (Some(arg_type), _) => Some(arg_type),
+        (None, Kind::Positional | Kind::Kwarg) => match infer(&field.ty) {
+            Some(variant) => {
+                let variant = Ident::new(variant, ident.span());
+                Some(quote! { ::clparser::ArgTypes::#variant })
+            },
+            None => return Err(syn::Error::new_spanned(&field.ty, "can not infer the argument type, add #[arg(ty = ..)]")),
+        },
+        (None, _) => None,
+    };
+    let name = name.unwrap_or(field_name);
+    Ok(Field {
+        value_name: value_name.unwrap_or_else(|| format!("<{}>", name)),
+        help: help.unwrap_or_else(|| doc(&field.attrs)),
+        ident,
+        ty: field.ty.clone(),
+        kind,
+        name,
+        keys,
+        arg_type,
+        constraints,
+        default,
+        error
+    })
+}
+
+impl Field {
+    fn input(&self) -> TokenStream2 {
+        let Field { name, arg_type, constraints, .. } = self;
+        quote! {
+            ::clparser::CLPInput::new(#arg_type, #name)
+                #(.constraint({
+                    #[allow(unused_imports)]
+                    use ::clparser::CLPConstraint::*;
+                    #constraints
+                }))*
+        }
+    }
+
+    /// Registers the keys of a keyword option or toggle.
+    fn kwargs(&self) -> TokenStream2 {
+        let Field { keys, help, value_name, error, .. } = self;
+        let kwarg = match self.kind {
+            Kind::Toggle => quote! { ::clparser::KwargTypes::Toggle([#help.to_string()]) },
+            Kind::Kwarg => {
+                let input = self.input();
+                let messages = quote! { [#help.to_string(), #value_name.to_string(), #error.to_string()] };
+                match self.default.is_some() || unwrap(&self.ty, "Option").is_some() {
+                    true => quote! { ::clparser::KwargTypes::Optional(#input, #messages) },
+                    false => quote! { ::clparser::KwargTypes::Important(#input, #messages) },
+                }
+            },
+            _ => return quote! {},
+        };
+        quote! {
+            for key in [#(#keys),*] {
+                keyvalues.insert(key.to_string(), #kwarg);
+            }
+        }
+    }
+
+    /// Stores a `default = ".."` in `result` unless the argument was given.
+    fn default(&self) -> TokenStream2 {
+        match &self.default {
+            Some(Expr::Lit(ExprLit { lit: Lit::Str(value), .. })) => {
+                let Field { name, .. } = self;
+                let input = self.input();
+                quote! {
+                    result.fill(#name, #input.parse_value(#value, now)?, ::clparser::CLPSource::Default);
+                }
+            },
+            _ => quote! {},
+        }
+    }
+
+    /// The expression filling the field from `result`.
+    fn value(&self) -> TokenStream2 {
+        let Field { ty, name, .. } = self;
+        let type_error = quote! { |err| ::clparser::CLPErrorKind::TypeError(format!("{}: {}", #name, err)) };
+        match self.kind {
+            Kind::Rest => quote! { result.default.iter().cloned().collect() },
+            Kind::Toggle => {
+                let names = self.keys.iter().map(|key| key.trim_start_matches('-'));
+                quote! {
+                    <#ty as ::clparser::CLPValue>::from_var(::clparser::CLPVar::Flag(0 #(+ result.count(#names)?)*)).map_err(#type_error)?
+                }
+            },
+            Kind::Positional | Kind::Kwarg => {
+                let default = match &self.default {
+                    Some(Expr::Lit(ExprLit { lit: Lit::Str(value), .. })) => {
+                        let input = self.input();
+                        quote! { <#ty as ::clparser::CLPValue>::from_var(#input.parse_value(#value, ::std::time::SystemTime::now())?).map_err(#type_error)? }
+                    },
+                    Some(default) => quote! { #default },
+                    None => return quote! { result.get::<#ty>(#name)? },
+                };
+                quote! {
+                    match result.get::<::std::option::Option<#ty>>(#name)? {
+                        ::std::option::Option::Some(value) => value,
+                        ::std::option::Option::None => #default,
+                    }
+                }
+            },
+        }
+    }
+}
+
+fn columns(rows: &[(String, String)]) -> String {
+    let width = rows.iter().map(|(left, _)| left.chars().count()).max().unwrap_or(0);
+    rows.iter()
+        .map(|(left, right)| format!("  {:width$}  {}", left, right, width = width).trim_end().to_string() + "\n")
+        .collect()
+}
+
+fn help(about: &str, fields: &[Field]) -> String {
+    let mut help = String::new();
+    if !about.is_empty() {
+        help += &format!("{}\n\n", about);
+    }
+    let positionals: Vec<(String, String)> = fields.iter()
+        .filter(|field| matches!(field.kind, Kind::Positional))
+        .map(|field| (format!("<{}>", field.name), field.help.clone()))
+        .collect();
+    let options: Vec<(String, String)> = fields.iter()
+        .filter(|field| matches!(field.kind, Kind::Kwarg | Kind::Toggle))
+        .map(|field| {
+            let mut left = field.keys.join(", ");
+            if let Kind::Kwarg = field.kind {
+                left += &format!(" {}", field.value_name);
+            }
+            let right = match &field.default {
+                Some(Expr::Lit(ExprLit { lit: Lit::Str(value), .. })) => format!("{} [default: {}]", field.help, value.value()),
+                Some(default) => format!("{} [default: {}]", field.help, quote!(#default)),
+                None => field.help.clone(),
+            };
+            (left, right.trim().to_string())
+        })
+        .collect();
+    // positionals come before any option
+    help += "Usage:";
+    for (name, _) in &positionals {
+        help += &format!(" {}", name);
+    }
+    if !options.is_empty() {
+        help += " [OPTIONS]";
+    }
+    if let Some(field) = fields.iter().find(|field| matches!(field.kind, Kind::Rest)) {
+        help += &format!(" [{}]...", field.name);
+    }
+    help += "\n";
+    if !positionals.is_empty() {
+        help += &format!("\nArguments:\n{}", columns(&positionals));
+    }
+    if !options.is_empty() {
+        help += &format!("\nOptions:\n{}", columns(&options));
+    }
+    help
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let named = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(input, "CLPArgs can only be derived for structs with named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(input, "CLPArgs can only be derived for structs with named fields")),
+    };
+    let fields = named.iter().map(parse_field).collect::<syn::Result<Vec<_>>>()?;
+    let mut seen = HashSet::new();
+    for (field, key) in fields.iter().flat_map(|field| field.keys.iter().map(move |key| (field, key))) {
+        if !seen.insert(key) {
+            return Err(syn::Error::new_spanned(&field.ident, format!("{} is used by more than one field", key)));
+        }
+    }
+    if fields.iter().filter(|field| matches!(field.kind, Kind::Rest)).count() > 1 {
+        return Err(syn::Error::new_spanned(input, "only one field can take the rest of the arguments"));
+    }
+
+    let positionals: Vec<TokenStream2> = fields.iter()
+        .filter(|field| matches!(field.kind, Kind::Positional))
+        .map(Field::input)
+        .collect();
+    let args = match positionals.is_empty() {
+        true => quote! { ::clparser::ArgsSettings::NONE },
+        false => quote! { ::clparser::ArgsSettings::Args(vec![#(#positionals),*]) },
+    };
+    let allow_more = fields.iter().any(|field| matches!(field.kind, Kind::Rest));
+    let kwargs = fields.iter().map(Field::kwargs);
+    let idents = fields.iter().map(|field| &field.ident);
+    let values = fields.iter().map(Field::value);
+    let defaults = fields.iter().map(Field::default);
+    let help = help(&doc(&input.attrs), &fields);
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::clparser::CLPArgs for #ident #type_generics #where_clause {
+            fn parser() -> ::clparser::CommandLineParser {
+                #[allow(unused_mut)]
+                let mut keyvalues = ::std::collections::HashMap::new();
+                #(#kwargs)*
+                ::clparser::CommandLineParser::new(#allow_more, #args, ::clparser::KwargSettings { keyvalues })
+            }
+
+            fn parse_from(result: &::clparser::CLPResult) -> ::std::result::Result<Self, ::clparser::CLPErrorKind> {
+                ::std::result::Result::Ok(Self {
+                    #(#idents: #values),*
+                })
+            }
+
+            #[allow(unused_variables)]
+            fn defaults(result: &mut ::clparser::CLPResult, now: ::std::time::SystemTime) -> ::std::result::Result<(), ::clparser::CLPErrorKind> {
+                #(#defaults)*
+                ::std::result::Result::Ok(())
+            }
+
+            fn help() -> ::std::string::String {
+                #help.to_string()
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use syn::parse_quote;
+    use crate::expand;
+
+    fn error(input: syn::DeriveInput) -> String {
+        expand(&input).err().map(|err| err.to_string()).unwrap_or_default()
+    }
+
+    #[test]
+    fn test_optional_positionals() {
+        let message = "positionals are required, add `long` or `short` for an optional value";
+        assert_eq!(error(parse_quote! { struct Args { input: Option<String> } }), message);
+        assert_eq!(error(parse_quote! { struct Args { #[arg(default = "a")] input: String } }), message);
+        assert_eq!(error(parse_quote! { struct Args { #[arg(long)] input: Option<String> } }), "");
+    }
+}
@@ -1,228 +1,866 @@
 mod parser;
 pub use parser::{CommandLineParser};
-pub use parser::types::{ArgsSettings, KwargSettings, KwargTypes, CLPInput, CLPVar, CLPResult, CLPErrorKind, ArgTypes};
-
-// #[cfg(test)]
-// mod test {
-//     use crate::{CommandLineParser, ArgsSettings, KwargSettings, KwargType};
-//     use crate::{CLPExpectedType, CLPType};
-//     use CLPExpectedType::{INT, UINT, VECSTRING, STRING};
-
-//     #[test]
-//     #[should_panic]
-//     fn test_args_mismatch() {
-//         let map = std::collections::HashMap::<String, KwargType>::new();
-//         let parser = CommandLineParser {
-//             allow_more: None,
-//             args: ArgsSettings::Args(vec![
-//                 CLPType {
-//                     object_type: INT,
-//                     name: "a".to_string()
-//                 },
-//                 CLPType {
-//                     object_type: UINT,
-//                     name: "b".to_string()
-//                 },
-//             ]),
-//             kwargs: KwargSettings {
-//                 keyvalues: map
-//             }
-//         };
-//         let args: Vec<String> = vec![
-//             "0".to_string(),
-//             "128".to_string(),
-//             "-128".to_string()
-//         ];
-//         let vars = parser.parse(&args);
-//         if let Err(name) = vars {
-//             panic!("{:?}", name);
-//         }
-//     }
-
-//     #[test]
-//     #[should_panic]
-//     fn test_under() {
-//         let map = std::collections::HashMap::<String, KwargType>::new();
-//         let parser = CommandLineParser {
-//             allow_more: None,
-//             args: ArgsSettings::Args(vec![
-//                 CLPType {
-//                     object_type: INT,
-//                     name: "a".to_string()
-//                 },
-//                 CLPType {
-//                     object_type: UINT,
-//                     name: "b".to_string()
-//                 },
-//             ]),
-//             kwargs: KwargSettings {
-//                 keyvalues: map
-//             }
-//         };
-//         let args: Vec<String> = vec![
-//             "0".to_string(),
-//             "128".to_string(),
-//         ];
-//         let vars = parser.parse(&args);
-//         if let Err(name) = vars {
-//             panic!("{:?}", name);
-//         }
-//     }
-    
-//     #[test]
-//     fn test_over_args_allow_more() {
-//         let map = std::collections::HashMap::<String, KwargType>::new();
-//         let parser = CommandLineParser {
-//             allow_more: Some(STRING),
-//             args: ArgsSettings::Args(vec![
-//                 CLPType {
-//                     object_type: INT,
-//                     name: "a".to_string()
-//                 },
-//                 CLPType {
-//                     object_type: UINT,
-//                     name: "b".to_string()
-//                 },
-//             ]),
-//             kwargs: KwargSettings {
-//                 keyvalues: map
-//             }
-//         };
-//         let args: Vec<String> = vec![
-//             "/target".to_string(),
-//             "7".to_string(),
-//             "9".to_string(),
-//             "11".to_string(),
-//         ];
-//         let vars = parser.parse(&args);
-//         if let Err(name) = vars {
-//             panic!("{:?}", name);
-//         }
-//     }
-
-    
-//     #[test]
-//     #[should_panic]
-//     fn test_over_args_allow_more_false() {
-//         let map = std::collections::HashMap::<String, KwargType>::new();
-//         let parser = CommandLineParser {
-//             allow_more: None,
-//             args: ArgsSettings::Args(vec![
-//                 CLPType {
-//                     object_type: INT,
-//                     name: "a".to_string()
-//                 },
-//                 CLPType {
-//                     object_type: UINT,
-//                     name: "b".to_string()
-//                 },
-//             ]),
-//             kwargs: KwargSettings {
-//                 keyvalues: map
-//             }
-//         };
-//         let args: Vec<String> = vec![
-//             "/target".to_string(),
-//             "7".to_string(),
-//             "9".to_string(),
-//             "11".to_string(),
-//         ];
-//         let vars = parser.parse(&args);
-//         if let Err(name) = vars {
-//             panic!("{:?}", name);
-//         }
-//     }
-
-//     #[test]
-//     fn test_vecstring() {
-//         let map = std::collections::HashMap::<String, KwargType>::new();
-//         let parser = CommandLineParser {
-//             allow_more: None,
-//             args: ArgsSettings::Args(vec![
-//                 CLPType {
-//                     object_type: VECSTRING,
-//                     name: "a".to_string()
-//                 },
-//             ]),
-//             kwargs: KwargSettings {
-//                 keyvalues: map
-//             }
-//         };
-
-
-//         let args: Vec<String> = vec![
-//             "/target".to_string(),
-//             "".to_string()
-//         ];
-
-//         let vars = parser.parse(&args);
-//         if let Err(_) = vars {
-//         } else {
-//             panic!("Empty String passed");
-//         }
-
-//         let args: Vec<String> = vec![
-//             "/target".to_string(),
-//             "xyz".to_string()
-//         ];
-        
-//         let vars = parser.parse(&args);
-//         if let Err(_) = vars {
-//         } else {
-//             panic!("'xyz' String passed");
-//         }
-
-//         let args: Vec<String> = vec![
-//             "/target".to_string(),
-//             "[]".to_string()
-//         ];
-        
-//         let vars = parser.parse(&args);
-//         if let Err(name) = vars {
-//             panic!("{:?}", name);
-//         }
-
-//         let args: Vec<String> = vec![
-//             "/target".to_string(),
-//             "[,]".to_string()
-//         ];
-        
-//         let vars = parser.parse(&args);
-//         if let Err(_) = vars {
-//         } else {
-//             panic!("should have gotten \"expected ' got ',' at the end of the argument [,]\"");
-//         }
-
-//         let args: Vec<String> = vec![
-//             "/target".to_string(),
-//             "['',]".to_string()
-//         ];
-        
-//         let vars = parser.parse(&args);
-//         if let Err(name) = vars {
-//             panic!("{:?}", name);
-//         }
-
-//         let args: Vec<String> = vec![
-//             "/target".to_string(),
-//             "['/'/'',]".to_string()
-//         ];
-        
-//         let vars = parser.parse(&args);
-//         if let Err(name) = vars {
-//             panic!("{:?}", name);
-//         }
-
-//         let args: Vec<String> = vec![
-//             "/target".to_string(),
-//             "['\\'',]".to_string()
-//         ];
-        
-//         let vars = parser.parse(&args);
-//         if let Err(_) = vars {
-//         }
-//         else {
-//             panic!("should have gotten \"expected , got ' at the end of the argument ['\\'',]\"");
-//         }
-//     }
-// }
\ No newline at end of file
+pub use parser::convert::CLPValue;
+pub use parser::derive::CLPArgs;
+#[cfg(feature = "derive")]
+pub use clparser_derive::CLPArgs;
+#[cfg(feature = "serde")]
+pub use parser::de::{from_result, from_var};
+pub use parser::types::{ArgsSettings, KwargSettings, KwargTypes, CLPInput, CLPVar, CLPResult, CLPError, CLPErrors, CLPErrorKind, CLPErrorReason, ArgTypes, CLPConstraint, CLPDuplicate, CLPTupleStyle, CLPVecSyntax, CLPRanges, CLPDate, CLPDateTime, CLPSource, CLPOccurrence};
+
+// the derive expands to `::clparser` paths
+#[cfg(all(test, feature = "derive"))]
+extern crate self as clparser;
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::net::{IpAddr, SocketAddr};
+    use std::time::Duration;
+    use crate::{CommandLineParser, ArgsSettings, KwargSettings, KwargTypes, CLPInput, CLPResult, CLPError, CLPConstraint, CLPDuplicate, CLPTupleStyle, CLPVar, CLPVecSyntax, CLPRanges, CLPDate, CLPDateTime, CLPErrorKind, CLPErrorReason, CLPSource};
+    use crate::ArgTypes::{self, INT, UINT, FLOAT, STRING, VECINT, VECUINT, VECFLOAT, DURATION, SIZE, IPV4, IPV6, IPADDR, SOCKETADDR, VECIPADDR, VECSOCKETADDR, MAP, TUPLE, VECVECINT, VECVECUINT, VECVECFLOAT, VECSTRING, DATE, DATETIME, RELTIME, PATH, OSSTRING};
+
+    fn positionals(inputs: Vec<CLPInput>) -> CommandLineParser {
+        CommandLineParser::new(false, ArgsSettings::Args(inputs), KwargSettings { keyvalues: HashMap::new() })
+    }
+
+    fn messages(hint: &str) -> [String; 3] {
+        [String::new(), hint.to_string(), String::new()]
+    }
+
+    fn options(args: ArgsSettings, kwargs: Vec<(&str, KwargTypes)>) -> CommandLineParser {
+        let keyvalues = kwargs.into_iter().map(|(key, kwarg)| (key.to_string(), kwarg)).collect();
+        CommandLineParser::new(false, args, KwargSettings { keyvalues })
+    }
+
+    fn one(object_type: ArgTypes, arg: &str) -> Result<CLPResult, CLPError> {
+        positionals(vec![CLPInput::new(object_type, "a")]).parse(["prog", arg])
+    }
+
+    fn message(result: Result<CLPResult, CLPError>) -> String {
+        match result {
+            Ok(result) => panic!("expected an error, got {:?}", result.args),
+            Err(err) => err.message,
+        }
+    }
+
+    #[test]
+    fn test_args_mismatch() {
+        let parser = positionals(vec![CLPInput::new(INT, "a"), CLPInput::new(UINT, "b")]);
+        assert!(parser.parse(["/target", "128", "-128"]).is_err());
+        let result = parser.parse(["/target", "-128", "128"]).unwrap();
+        assert_eq!(result.get::<i32>("a").unwrap(), -128);
+        assert_eq!(result.get::<u32>("b").unwrap(), 128);
+    }
+
+    #[test]
+    fn test_under() {
+        let parser = positionals(vec![CLPInput::new(INT, "a"), CLPInput::new(UINT, "b")]);
+        assert_eq!(message(parser.parse(["/target", "0"])), "Didn't get input for UINT(b)");
+    }
+
+    #[test]
+    fn test_over_args_allow_more() {
+        let mut parser = positionals(vec![CLPInput::new(INT, "a"), CLPInput::new(UINT, "b")]);
+        assert_eq!(message(parser.parse(["/target", "7", "9", "11"])), "Unexpected input 11");
+        parser.allow_more = true;
+        assert_eq!(parser.parse(["/target", "7", "9", "11"]).unwrap().default, ["11"]);
+    }
+
+    #[test]
+    fn test_kwargs() {
+        let parser = options(ArgsSettings::Args(vec![CLPInput::new(STRING, "file")]), vec![
+            ("--threads", KwargTypes::Important(CLPInput::new(UINT, "threads"), messages("<n>"))),
+            ("--ratio", KwargTypes::Optional(CLPInput::new(FLOAT, "ratio"), messages("<ratio>"))),
+            ("-v", KwargTypes::Toggle([String::new()]))
+        ]);
+        let result = parser.parse(["prog", "f.txt", "--threads", "4", "-v", "-v"]).unwrap();
+        assert_eq!(result.get::<String>("file").unwrap(), "f.txt");
+        assert_eq!(result.get::<u8>("threads").unwrap(), 4);
+        assert_eq!(result.get::<Option<f64>>("ratio").unwrap(), None);
+        assert_eq!(result.count("v").unwrap(), 2);
+        assert_eq!(message(parser.parse(["prog", "f.txt"])), "Didn't get input for --threads");
+        assert_eq!(message(parser.parse(["prog", "f.txt", "--threads"])), "Expected --threads <n> got End Of Input");
+        assert_eq!(message(parser.parse(["prog", "--threads", "4", "f.txt"])), "Expected STRING(file) got --threads");
+    }
+
+    #[test]
+    fn test_all_numbering() {
+        let parser = options(ArgsSettings::ALL(CLPInput::new(INT, "n")), vec![
+            ("-v", KwargTypes::Toggle([String::new()]))
+        ]);
+        let result = parser.parse(["prog", "1", "2", "-v"]).unwrap();
+        assert_eq!(result.get::<i8>("n0").unwrap(), 1);
+        assert_eq!(result.get::<i8>("n1").unwrap(), 2);
+        assert!(result.flag("v").unwrap());
+    }
+
+    #[test]
+    fn test_range_constraints() {
+        let threads = || CLPInput::new(INT, "threads").constraint(CLPConstraint::IntRange(Some(1), Some(256)));
+        assert!(positionals(vec![threads()]).parse(["prog", "256"]).is_ok());
+        assert_eq!(message(positionals(vec![threads()]).parse(["prog", "0"])), "0 for threads is outside of the allowed interval [1, 256]");
+        let open = CLPInput::new(UINT, "n").constraint(CLPConstraint::UIntRange(None, Some(9)));
+        assert_eq!(message(positionals(vec![open]).parse(["prog", "10"])), "10 for n is outside of the allowed interval (-inf, 9]");
+        let parser = options(ArgsSettings::NONE, vec![
+            ("--threads", KwargTypes::Important(threads(), messages("<n>")))
+        ]);
+        assert_eq!(message(parser.parse(["prog", "--threads", "300"])), "300 for threads is outside of the allowed interval [1, 256]");
+    }
+
+    #[test]
+    fn test_finite_constraints() {
+        let ratio = || CLPInput::new(FLOAT, "ratio")
+            .constraint(CLPConstraint::Finite)
+            .constraint(CLPConstraint::FloatRange(Some(0.0), Some(1.0)));
+        assert_eq!(positionals(vec![ratio()]).parse(["prog", "0.5"]).unwrap().get::<f64>("ratio").unwrap(), 0.5);
+        assert_eq!(message(positionals(vec![ratio()]).parse(["prog", "NaN"])), "NaN for ratio is not a finite number");
+        assert_eq!(message(positionals(vec![ratio()]).parse(["prog", "inf"])), "inf for ratio is not a finite number");
+        assert_eq!(message(positionals(vec![ratio()]).parse(["prog", "1.5"])), "1.5 for ratio is outside of the allowed interval [0.0, 1.0]");
+    }
+
+    #[test]
+    fn test_vector_constraints() {
+        let ints = CLPInput::new(VECINT, "xs").constraint(CLPConstraint::IntRange(Some(0), None));
+        assert_eq!(message(positionals(vec![ints]).parse(["prog", "[1, -2]"])), "-2 at index 1 of xs is outside of the allowed interval [0, +inf)");
+        let floats = CLPInput::new(VECFLOAT, "xs").constraint(CLPConstraint::Finite);
+        assert_eq!(message(positionals(vec![floats]).parse(["prog", "[1, inf]"])), "inf at index 1 of xs is not a finite number");
+    }
+
+    #[test]
+    fn test_custom_constraint() {
+        fn even(var: &CLPVar) -> Result<(), String> {
+            match var {
+                CLPVar::Int(v) if v % 2 == 0 => Ok(()),
+                _ => Err("odd number".to_string()),
+            }
+        }
+        let input = || CLPInput::new(INT, "n").constraint(CLPConstraint::Custom(even));
+        assert!(positionals(vec![input()]).parse(["prog", "4"]).is_ok());
+        assert_eq!(message(positionals(vec![input()]).parse(["prog", "3"])), "odd number for n");
+        let string = CLPInput::new(STRING, "s").constraint(CLPConstraint::Finite);
+        assert_eq!(message(positionals(vec![string]).parse(["prog", "x"])), "constraint finite can not be applied to String(\"x\") of s");
+    }
+
+    #[test]
+    fn test_int_literals() {
+        assert_eq!(one(INT, "0xFF").unwrap().get::<i32>("a").unwrap(), 255);
+        assert_eq!(one(INT, "0o755").unwrap().get::<i32>("a").unwrap(), 0o755);
+        assert_eq!(one(INT, "0b1010").unwrap().get::<i32>("a").unwrap(), 10);
+        assert_eq!(one(INT, "1_000_000").unwrap().get::<i32>("a").unwrap(), 1_000_000);
+        assert_eq!(one(INT, "+5").unwrap().get::<i32>("a").unwrap(), 5);
+        assert_eq!(one(INT, "-0x10").unwrap().get::<i32>("a").unwrap(), -16);
+        assert_eq!(one(UINT, "0XfF").unwrap().get::<u32>("a").unwrap(), 255);
+        assert_eq!(one(VECUINT, "[0x10, 1_0, +0b1]").unwrap().get::<Vec<u32>>("a").unwrap(), [16, 10, 1]);
+        for arg in ["_1", "1_", "0x", "0x_1", "+-5", "0b2", "1__0x"] {
+            assert_eq!(message(one(INT, arg)), format!("unable to parse {:?} as integer", arg));
+        }
+        assert_eq!(message(one(INT, "--5")), "Unknown option --5");
+        assert_eq!(message(one(UINT, "-1")), "unable to parse \"-1\" as unsigned integer");
+        assert_eq!(message(one(VECINT, "[1, 0xG]")), "Expected INT got \"0xG\" in arg [1, 0xG]");
+    }
+
+    #[test]
+    fn test_decimal_only() {
+        let parser = positionals(vec![CLPInput::new(INT, "a").decimal_only()]);
+        assert_eq!(parser.parse(["prog", "-12"]).unwrap().get::<i32>("a").unwrap(), -12);
+        assert!(parser.parse(["prog", "0xFF"]).is_err());
+        assert!(parser.parse(["prog", "1_000"]).is_err());
+    }
+
+    #[test]
+    fn test_durations() {
+        let duration = |arg: &str| one(DURATION, arg).unwrap().get::<Duration>("a").unwrap();
+        assert_eq!(duration("30s"), Duration::from_secs(30));
+        assert_eq!(duration("1h30m"), Duration::from_secs(5400));
+        assert_eq!(duration("250ms"), Duration::from_millis(250));
+        assert_eq!(duration("1.5d"), Duration::from_secs(129600));
+        assert_eq!(duration("1us5ns"), Duration::from_nanos(1005));
+        assert_eq!(message(one(DURATION, "30")), "unable to parse \"30\" as duration, missing unit after 30, expected one of ns, us, ms, s, m, h, d");
+        assert_eq!(message(one(DURATION, "5x")), "unable to parse \"5x\" as duration, unknown unit \"x\", expected one of ns, us, ms, s, m, h, d");
+        assert_eq!(message(one(DURATION, "5é")), "unable to parse \"5é\" as duration, missing unit after 5");
+        assert_eq!(message(one(DURATION, "h")), "unable to parse \"h\" as duration, expected a number at \"h\"");
+        assert_eq!(message(one(DURATION, "")), "unable to parse \"\" as duration, expected a duration got an empty string");
+        assert_eq!(message(one(DURATION, "99999999999999999999d")), "unable to parse \"99999999999999999999d\" as duration, duration is too large");
+    }
+
+    #[test]
+    fn test_bare_durations() {
+        let parser = positionals(vec![CLPInput::new(DURATION, "a").duration_unit(Duration::from_millis(1))]);
+        assert_eq!(parser.parse(["prog", "1500"]).unwrap().get::<Duration>("a").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parser.parse(["prog", "2s"]).unwrap().get::<Duration>("a").unwrap(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_sizes() {
+        let size = |arg: &str| one(SIZE, arg).unwrap().get::<u64>("a").unwrap();
+        assert_eq!(size("4k"), 4_000);
+        assert_eq!(size("512MiB"), 512 << 20);
+        assert_eq!(size("1.5G"), 1_500_000_000);
+        assert_eq!(size("1.5 KiB"), 1536);
+        assert_eq!(size("2tb"), 2_000_000_000_000);
+        assert_eq!(size("100"), 100);
+        assert_eq!(size("0.5b"), 0);
+        assert_eq!(message(one(SIZE, "16EiB")), "unable to parse \"16EiB\" as size, unknown unit \"EiB\", expected one of B, K, KB, KiB, M, MB, MiB, G, GB, GiB, T, TB, TiB");
+        assert_eq!(message(one(SIZE, "MiB")), "unable to parse \"MiB\" as size, expected a number got \"MiB\"");
+        assert_eq!(message(one(SIZE, "20000000TiB")), "unable to parse \"20000000TiB\" as size, 20000000TiB overflows a 64 bit byte count");
+        assert_eq!(message(one(SIZE, "1.2.3k")), "unable to parse \"1.2.3k\" as size, expected a number got \"1.2.3\"");
+    }
+
+    #[test]
+    fn test_addresses() {
+        assert_eq!(one(IPV4, "10.0.0.1").unwrap().get::<IpAddr>("a").unwrap(), IpAddr::from([10, 0, 0, 1]));
+        assert_eq!(one(IPV6, "::1").unwrap().get::<IpAddr>("a").unwrap(), "::1".parse::<IpAddr>().unwrap());
+        assert_eq!(one(IPADDR, "::1").unwrap().get::<IpAddr>("a").unwrap(), "::1".parse::<IpAddr>().unwrap());
+        assert_eq!(message(one(IPV4, "::1")), "unable to parse \"::1\" as IPv4 address");
+        assert_eq!(message(one(IPV6, "10.0.0.1")), "unable to parse \"10.0.0.1\" as IPv6 address");
+        assert_eq!(one(SOCKETADDR, "0.0.0.0:8080").unwrap().get::<SocketAddr>("a").unwrap(), "0.0.0.0:8080".parse::<SocketAddr>().unwrap());
+        assert_eq!(one(SOCKETADDR, "[::1]:9000").unwrap().get::<SocketAddr>("a").unwrap(), "[::1]:9000".parse::<SocketAddr>().unwrap());
+        assert_eq!(message(one(SOCKETADDR, "10.0.0.1")), "unable to parse \"10.0.0.1\" as socket address");
+        let peers = one(VECSOCKETADDR, "[10.0.0.1:1, [::1]:2]").unwrap().get::<Vec<SocketAddr>>("a").unwrap();
+        assert_eq!(peers, ["10.0.0.1:1".parse::<SocketAddr>().unwrap(), "[::1]:2".parse().unwrap()]);
+        assert_eq!(message(one(VECIPADDR, "[10.0.0.1, x]")), "Expected IPADDR got \"x\" in arg [10.0.0.1, x]");
+    }
+
+    #[test]
+    fn test_default_port() {
+        let parser = positionals(vec![CLPInput::new(VECSOCKETADDR, "a").default_port(80)]);
+        let peers = parser.parse(["prog", "[10.0.0.1, [::1], 10.0.0.2:8080]"]).unwrap().get::<Vec<SocketAddr>>("a").unwrap();
+        assert_eq!(peers, ["10.0.0.1:80".parse::<SocketAddr>().unwrap(), "[::1]:80".parse().unwrap(), "10.0.0.2:8080".parse().unwrap()]);
+        assert!(parser.parse(["prog", "[example.com]"]).is_err());
+    }
+
+    fn defines(duplicate_keys: CLPDuplicate) -> CommandLineParser {
+        let input = CLPInput::new(MAP(Box::new(INT)), "defines").duplicate_keys(duplicate_keys);
+        options(ArgsSettings::NONE, vec![("-D", KwargTypes::Optional(input, messages("<key=value>")))])
+    }
+
+    #[test]
+    fn test_maps() {
+        let parser = defines(CLPDuplicate::LastWins);
+        let result = parser.parse(["prog", "-Dopt.level=3", "-D", "target=0x10", "-D[a=1, b=2]"]).unwrap();
+        let map = result.get::<HashMap<String, i32>>("defines").unwrap();
+        assert_eq!(map, HashMap::from([("opt.level".to_string(), 3), ("target".to_string(), 16), ("a".to_string(), 1), ("b".to_string(), 2)]));
+        assert_eq!(parser.parse(["prog", "-Da=1", "-Da=2"]).unwrap().get::<HashMap<String, i32>>("defines").unwrap()["a"], 2);
+        let first_wins = defines(CLPDuplicate::FirstWins).parse(["prog", "-Da=1", "-Da=2", "-D[a=3]"]).unwrap();
+        assert_eq!(first_wins.get::<HashMap<String, i32>>("defines").unwrap()["a"], 1);
+        assert_eq!(message(defines(CLPDuplicate::Error).parse(["prog", "-D[a=1, a=2]"])), "duplicate key \"a\"");
+        assert_eq!(message(defines(CLPDuplicate::Error).parse(["prog", "-Da=1", "-Da=2"])), "duplicate key \"a\"");
+        assert_eq!(message(parser.parse(["prog", "-D[a=1, =2]"])), "Expected KEY=VALUE got \"=2\" in arg [a=1, =2]");
+        assert_eq!(message(parser.parse(["prog", "-Dflag"])), "Expected KEY=VALUE got \"flag\" in arg flag");
+        assert_eq!(parser.parse(["prog", "-D[a=1,]"]).unwrap().get::<HashMap<String, i32>>("defines").unwrap(), HashMap::from([("a".to_string(), 1)]));
+        assert_eq!(parser.parse(["prog", "-D[ ]"]).unwrap().get::<HashMap<String, i32>>("defines").unwrap().len(), 0);
+        assert_eq!(message(parser.parse(["prog", "-D[a=1,,b=2]"])), "empty element at index 1 in arg [a=1,,b=2]");
+        assert_eq!(message(parser.parse(["prog", "-D[a=1,,]"])), "empty element at index 1 in arg [a=1,,]");
+        assert_eq!(message(parser.parse(["prog", "-Da=x"])), "unable to parse \"x\" as integer");
+        assert_eq!(parser.parse(["prog", "-D[]"]).unwrap().get::<HashMap<String, i32>>("defines").unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_quoted_maps() {
+        let parser = positionals(vec![CLPInput::new(MAP(Box::new(STRING)), "a")]);
+        let map = |arg: &str| parser.parse(["prog", arg]).unwrap().get::<HashMap<String, String>>("a").unwrap();
+        assert_eq!(map("[a='x,y', b=\"p=q\"]"), HashMap::from([("a".to_string(), "x,y".to_string()), ("b".to_string(), "p=q".to_string())]));
+        assert_eq!(map("['k=v'=1, c = d ]"), HashMap::from([("k=v".to_string(), "1".to_string()), ("c".to_string(), "d".to_string())]));
+        assert_eq!(map("a='tab\\there'"), HashMap::from([("a".to_string(), "tab\there".to_string())]));
+        assert_eq!(message(parser.parse(["prog", "[a='x]"])), "expected ' got End Of Argument in arg [a='x]");
+    }
+
+    #[test]
+    fn test_tuples() {
+        let record = || TUPLE(vec![UINT, STRING, FLOAT], CLPTupleStyle::Bracketed);
+        let result = one(record(), "[10, 'a, b', 0.5]").unwrap();
+        match result.get_var("a").unwrap() {
+            CLPVar::Tuple(values) => match values.as_slice() {
+                [CLPVar::UInt(10), CLPVar::String(label), CLPVar::Float(ratio)] => assert_eq!((label.as_str(), *ratio), ("a, b", 0.5)),
+                values => panic!("unexpected elements {:?}", values),
+            },
+            var => panic!("expected a tuple, got {:?}", var),
+        }
+        assert_eq!(message(one(record(), "[10, label, x]")), "element 2 of [10, label, x]: unable to parse \"x\" as float");
+        assert_eq!(message(one(record(), "[10, label]")), "Expected [UINT, STRING, FLOAT] with 3 elements got 2 in arg [10, label]");
+        assert_eq!(message(one(record(), "10, label, 1")), "Expected [UINT, STRING, FLOAT] got \"10, label, 1\"");
+
+        let size = || TUPLE(vec![UINT, UINT], CLPTupleStyle::Separated('x'));
+        assert_eq!(one(size(), "1920x1080").unwrap().get::<Vec<u32>>("a").unwrap(), [1920, 1080]);
+        assert_eq!(message(one(size(), "1920x")), "element 1 of 1920x: unable to parse \"\" as unsigned integer");
+        assert_eq!(message(one(size(), "1920x1080x2")), "Expected UINTxUINT with 2 elements got 3 in arg 1920x1080x2");
+    }
+
+    #[test]
+    fn test_nested_vectors() {
+        assert_eq!(one(VECVECINT, "[[1, 2], [3, -4]]").unwrap().get::<Vec<Vec<i32>>>("a").unwrap(), [[1, 2], [3, -4]]);
+        assert_eq!(one(VECVECFLOAT, "[[0.5], [], [1, 2]]").unwrap().get::<Vec<Vec<f64>>>("a").unwrap(), [vec![0.5], vec![], vec![1.0, 2.0]]);
+        assert_eq!(message(one(VECVECUINT, "[[1, 2], 3]")), "Expected [[...UINT...]] got \"3\"");
+        assert_eq!(message(one(VECVECINT, "[[1, x]]")), "Expected INT got \"x\" in arg [1, x]");
+
+        let matrix = |constraint: CLPConstraint, arg: &str| positionals(vec![CLPInput::new(VECVECINT, "m").constraint(constraint)]).parse(["prog", arg]);
+        assert!(matrix(CLPConstraint::Rectangular, "[[1, 2], [3, 4]]").is_ok());
+        assert_eq!(message(matrix(CLPConstraint::Rectangular, "[[1, 2], [3]]")), "row 1 of m has 1 columns but row 0 has 2");
+        assert!(matrix(CLPConstraint::Shape(Some(2), None), "[[1], [2, 3]]").is_ok());
+        assert_eq!(message(matrix(CLPConstraint::Shape(Some(3), Some(3)), "[[1, 2], [3, 4]]")), "m has 2 rows, expected 3");
+        assert_eq!(message(matrix(CLPConstraint::Shape(None, Some(2)), "[[1, 2], [3]]")), "row 1 of m has 1 columns, expected 2");
+        assert_eq!(message(matrix(CLPConstraint::IntRange(Some(0), None), "[[1, 2], [-3]]")), "-3 at index [1][0] of m is outside of the allowed interval [0, +inf)");
+    }
+
+    #[test]
+    fn test_vector_syntax() {
+        let ints = |syntax: CLPVecSyntax, arg: &str| positionals(vec![CLPInput::new(VECINT, "a").vec_syntax(syntax)]).parse(["prog", arg]);
+        let values = |syntax: CLPVecSyntax, arg: &str| ints(syntax, arg).unwrap().get::<Vec<i32>>("a").unwrap();
+        assert_eq!(values(CLPVecSyntax::Bracketed, "[]"), []);
+        assert_eq!(values(CLPVecSyntax::Bracketed, "[ ]"), []);
+        assert_eq!(values(CLPVecSyntax::Bracketed, "[1, 2,]"), [1, 2]);
+        assert_eq!(values(CLPVecSyntax::Bare, "1,2, 3"), [1, 2, 3]);
+        assert_eq!(values(CLPVecSyntax::Bare, ""), []);
+        assert_eq!(values(CLPVecSyntax::Separator(':'), "1: 2 :3:"), [1, 2, 3]);
+        assert_eq!(values(CLPVecSyntax::Separator(','), "1, 2"), [1, 2]);
+        assert_eq!(values(CLPVecSyntax::Whitespace, " 1 2\t3 "), [1, 2, 3]);
+        assert_eq!(message(ints(CLPVecSyntax::Bracketed, "[1,,2]")), "empty element at index 1 in arg [1,,2]");
+        assert_eq!(message(ints(CLPVecSyntax::Bracketed, "[1, 2,,]")), "empty element at index 2 in arg [1, 2,,]");
+        assert_eq!(message(ints(CLPVecSyntax::Separator(':'), ":1")), "empty element at index 0 in arg :1");
+        assert_eq!(message(ints(CLPVecSyntax::Bracketed, "1, 2")), "Expected [...INT...] got \"1, 2\"");
+
+        let paths = positionals(vec![CLPInput::new(VECSTRING, "a").vec_syntax(CLPVecSyntax::Separator(':'))]);
+        assert_eq!(paths.parse(["prog", "/bin:/usr/bin"]).unwrap().get::<Vec<String>>("a").unwrap(), ["/bin", "/usr/bin"]);
+    }
+
+    #[test]
+    fn test_ranges() {
+        let ints = |ranges: CLPRanges, arg: &str| {
+            let input = CLPInput::new(VECINT, "a").vec_syntax(CLPVecSyntax::Bare).ranges(ranges);
+            positionals(vec![input]).parse(["prog", arg])
+        };
+        let values = |ranges: CLPRanges, arg: &str| ints(ranges, arg).unwrap().get::<Vec<i32>>("a").unwrap();
+        assert_eq!(values(CLPRanges::default(), "1-5,8,10..12"), [1, 2, 3, 4, 5, 8, 10, 11]);
+        assert_eq!(values(CLPRanges::default(), "10..=12,-3--1"), [10, 11, 12, -3, -2, -1]);
+        assert_eq!(values(CLPRanges::default(), "0..100:10"), [0, 10, 20, 30, 40, 50, 60, 70, 80, 90]);
+        assert_eq!(values(CLPRanges::default(), "5-1:2,3..3"), [5, 3, 1]);
+        assert_eq!(values(CLPRanges { dedup_sort: true, ..CLPRanges::default() }, "5,1-3,2"), [1, 2, 3, 5]);
+        assert_eq!(message(ints(CLPRanges { reject_reversed: true, ..CLPRanges::default() }, "1,5-1")), "range 5-1 is reversed in arg 1,5-1");
+        assert_eq!(message(ints(CLPRanges { reject_overlap: true, ..CLPRanges::default() }, "1-5,3-7")), "3-7 overlaps 1-5 in arg 1-5,3-7");
+        assert!(ints(CLPRanges { reject_overlap: true, ..CLPRanges::default() }, "1-5,6-7").is_ok());
+        assert!(ints(CLPRanges { reject_overlap: true, ..CLPRanges::default() }, "0..10:5,3").is_ok());
+        assert_eq!(message(ints(CLPRanges { reject_overlap: true, ..CLPRanges::default() }, "0..10:5,5")), "5 overlaps 0..10:5 in arg 0..10:5,5");
+        assert_eq!(message(ints(CLPRanges::default(), "1-5:0")), "Expected a positive step got \"0\" in arg 1-5:0");
+        assert_eq!(message(ints(CLPRanges::default(), "1-x")), "Expected INT got \"x\" in arg 1-x");
+        assert_eq!(message(ints(CLPRanges::default(), "0..=1048576")), "arg 0..=1048576 expands to more than 1048576 elements");
+        assert_eq!(ints(CLPRanges::default(), "0-1048575").unwrap().get_vec_int("a").unwrap().len(), 1048576);
+        assert_eq!(message(ints(CLPRanges::default(), "0-1048575,0")), "arg 0-1048575,0 expands to more than 1048576 elements");
+        let repeated = ["0-1048575"; 8].join(",");
+        assert!(message(ints(CLPRanges::default(), &repeated)).ends_with("expands to more than 1048576 elements"));
+
+        let uints = positionals(vec![CLPInput::new(VECUINT, "a").ranges(CLPRanges::default())]);
+        assert_eq!(uints.parse(["prog", "[0x10-0x12]"]).unwrap().get::<Vec<u8>>("a").unwrap(), [16, 17, 18]);
+    }
+
+    #[test]
+    fn test_vecstring() {
+        let strings = |arg: &str| one(VECSTRING, arg).unwrap().get::<Vec<String>>("a").unwrap();
+        assert_eq!(strings("[]"), Vec::<String>::new());
+        assert_eq!(strings("['/usr/bin', \"a\\tb\", c d, 'x,y',]"), ["/usr/bin", "a\tb", "c d", "x,y"]);
+        assert_eq!(strings("['it\\'s', \"\\u{e9}\\n\", '[a]']"), ["it's", "é\n", "[a]"]);
+        assert_eq!(strings("['']"), [""]);
+        assert!(one(VECSTRING, "").is_err());
+        assert!(one(VECSTRING, "xyz").is_err());
+        assert_eq!(message(one(VECSTRING, "[,]")), "empty element at index 0 in arg [,]");
+        assert_eq!(message(one(VECSTRING, "['a' b]")), "unexpected \" b\" after closing ' at index 0 in arg ['a' b]");
+        assert_eq!(message(one(VECSTRING, "['a]")), "expected ' got End Of Argument at index 0 in arg ['a]");
+        assert_eq!(message(one(VECSTRING, "['\\q']")), "unknown escape \\q at index 0 in arg ['\\q']");
+        assert_eq!(message(one(VECSTRING, "['\\u{110000}']")), "invalid unicode escape \\u{110000} at index 0 in arg ['\\u{110000}']");
+    }
+
+    #[test]
+    fn test_vecstring_legacy() {
+        let parser = positionals(vec![CLPInput::new(VECSTRING, "a").legacy_escape()]);
+        let strings = |arg: &str| parser.parse(["prog", arg]).unwrap().get::<Vec<String>>("a").unwrap();
+        assert_eq!(strings("[]"), Vec::<String>::new());
+        assert_eq!(strings("['',]"), [""]);
+        assert_eq!(strings("['/'/'', 'a/b']"), ["''", "ab"]);
+        assert_eq!(message(parser.parse(["prog", "[,]"])), "expected ' got ',' at the end of the argument [,]");
+        assert_eq!(message(parser.parse(["prog", "['\\'',]"])), "expected , got ' at the end of the argument ['\\'',]");
+        assert_eq!(message(parser.parse(["prog", "['a/']"])), "expected ' got End Of Argument ['a/']");
+    }
+
+    #[test]
+    fn test_sequence_constraints() {
+        let ints = |constraint: CLPConstraint, arg: &str| positionals(vec![CLPInput::new(VECINT, "xs").constraint(constraint)]).parse(["prog", arg]);
+        assert!(ints(CLPConstraint::Length(Some(1), None), "[1]").is_ok());
+        assert_eq!(message(ints(CLPConstraint::Length(Some(1), None), "[]")), "xs has 0 elements, expected [1, +inf)");
+        assert_eq!(message(ints(CLPConstraint::Length(Some(3), Some(3)), "[1, 2]")), "xs has 2 elements, expected 3");
+        assert_eq!(message(ints(CLPConstraint::Unique, "[1, 2, 1]")), "1 at index 2 of xs repeats index 0");
+        assert_eq!(message(ints(CLPConstraint::Ascending, "[1, 3, 2]")), "2 at index 2 of xs is smaller than 3, expected ascending order");
+        assert_eq!(message(ints(CLPConstraint::Descending, "[3, 4]")), "4 at index 1 of xs is larger than 3, expected descending order");
+        assert!(ints(CLPConstraint::Ascending, "[1, 1, 2]").is_ok());
+        assert_eq!(message(ints(CLPConstraint::Length(None, Some(1)), "5")), "Expected [...INT...] got \"5\"");
+    }
+
+    #[test]
+    fn test_set_mode() {
+        let parser = positionals(vec![CLPInput::new(VECSTRING, "a").set().constraint(CLPConstraint::Length(None, Some(2)))]);
+        assert_eq!(parser.parse(["prog", "[b, a, b, a]"]).unwrap().get::<Vec<String>>("a").unwrap(), ["b", "a"]);
+        let floats = positionals(vec![CLPInput::new(VECFLOAT, "a").set()]);
+        assert_eq!(floats.parse(["prog", "[0, -0, 1.5, 1.5]"]).unwrap().get::<Vec<f64>>("a").unwrap(), [0.0, 1.5]);
+    }
+
+    #[test]
+    fn test_merged_map_constraints() {
+        let input = CLPInput::new(MAP(Box::new(INT)), "defines").constraint(CLPConstraint::Length(None, Some(2)));
+        let parser = options(ArgsSettings::NONE, vec![("-D", KwargTypes::Optional(input, messages("<key=value>")))]);
+        assert!(parser.parse(["prog", "-Da=1", "-Db=2", "-Da=3"]).is_ok());
+        assert_eq!(message(parser.parse(["prog", "-Da=1", "-Db=2", "-Dc=3"])), "defines has 3 elements, expected (-inf, 2]");
+        let errors = parser.parse_all(["prog", "-Da=1", "-Db=2", "-Dc=3"]).unwrap_err();
+        assert_eq!(errors.result.get::<HashMap<String, i32>>("defines").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(one(DATE, "2024-02-29").unwrap().get::<CLPDate>("a").unwrap(), CLPDate { year: 2024, month: 2, day: 29 });
+        assert_eq!(message(one(DATE, "2023-02-29")), "unable to parse \"2023-02-29\" as date, day 29 is out of range for 2023-02");
+        assert_eq!(message(one(DATE, "2023-13-01")), "unable to parse \"2023-13-01\" as date, month 13 is out of range");
+        assert_eq!(message(one(DATE, "2023-1-01")), "unable to parse \"2023-1-01\" as date, expected YYYY-MM-DD");
+
+        let datetime = |arg: &str| one(DATETIME, arg).unwrap().get::<CLPDateTime>("a").unwrap();
+        let utc = datetime("2026-10-18T12:00:00Z");
+        assert_eq!((utc.hour, utc.minute, utc.second, utc.offset), (12, 0, 0, Some(0)));
+        assert_eq!(utc.timestamp(), 1792324800);
+        assert_eq!(datetime("2026-10-18 14:00+02:00").timestamp(), 1792324800);
+        assert_eq!(datetime("2026-10-18T07:00:00.5-0500").to_string(), "2026-10-18T07:00:00.5-05:00");
+        assert_eq!(datetime("2026-10-18T12:00:00.1234567891").nanosecond, 123456789);
+        assert_eq!(datetime("2026-10-18T12:00").offset, None);
+        assert_eq!(message(one(DATETIME, "2026-10-18T24:00")), "unable to parse \"2026-10-18T24:00\" as date and time, time 24:00:00 is out of range");
+        assert_eq!(message(one(DATETIME, "2026-10-18T12:00+25")), "unable to parse \"2026-10-18T12:00+25\" as date and time, invalid UTC offset \"+25\"");
+    }
+
+    #[test]
+    fn test_dates_non_ascii() {
+        for arg in ["2026-10-18T12:00+1é0", "2026-10-18T12:00+1é:0", "2026-10-18T12:00+é", "2026-10-18T12:00:00.12345678é", "2026-10-18T12:00:00.123456789abc", "2026-10-18T12:00:00.123456789é"] {
+            assert!(one(DATETIME, arg).unwrap_err().message.starts_with("unable to parse"), "{}", arg);
+        }
+        for arg in ["abcé", "éabc-", "202é-01-01", "2026é"] {
+            assert!(one(RELTIME, arg).unwrap_err().message.starts_with("unable to parse"), "{}", arg);
+        }
+    }
+
+    #[test]
+    fn test_relative_times() {
+        let now = std::time::UNIX_EPOCH + Duration::from_secs(1792324800);
+        let parser = positionals(vec![CLPInput::new(RELTIME, "since")]).with_now(now);
+        let time = |arg: &str| parser.parse(["prog", arg]).unwrap().get::<CLPDateTime>("since").unwrap().to_string();
+        assert_eq!(time("now"), "2026-10-18T12:00:00Z");
+        assert_eq!(time("3d"), "2026-10-15T12:00:00Z");
+        assert_eq!(time("-1h30m"), "2026-10-18T10:30:00Z");
+        assert_eq!(time("2h ago"), "2026-10-18T10:00:00Z");
+        assert_eq!(time("+1d"), "2026-10-19T12:00:00Z");
+        assert_eq!(time("2026-10-01"), "2026-10-01T00:00:00");
+        assert_eq!(time("2026-10-01T08:00Z"), "2026-10-01T08:00:00Z");
+        assert_eq!(message(parser.parse(["prog", "yesterday"])), "unable to parse \"yesterday\" as time, expected a date, a date and time or a relative time like 3d");
+        assert_eq!(message(parser.parse(["prog", "+5000000d"])), "unable to parse \"+5000000d\" as time, +5000000d is too far from now");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_non_utf8() {
+        use std::ffi::OsString;
+        use std::os::unix::ffi::OsStringExt;
+        let raw = || OsString::from_vec(b"caf\xe9.txt".to_vec());
+        let parser = positionals(vec![CLPInput::new(PATH, "path"), CLPInput::new(OSSTRING, "raw")]);
+        let result = parser.parse([OsString::from("prog"), raw(), raw()]).unwrap();
+        assert_eq!(result.get::<std::path::PathBuf>("path").unwrap().into_os_string(), raw());
+        assert_eq!(result.get::<OsString>("raw").unwrap(), raw());
+        assert_eq!(message(positionals(vec![CLPInput::new(STRING, "s")]).parse([OsString::from("prog"), raw()])), "\"caf\\xE9.txt\" is not valid UTF-8, expected STRING");
+        let mut rest = positionals(vec![]);
+        rest.allow_more = true;
+        assert_eq!(message(rest.parse([OsString::from("prog"), raw()])), "\"caf\\xE9.txt\" is not valid UTF-8, expected STRING");
+        let arg0 = positionals(vec![]).parse([OsString::from_vec(b"pr\xffg".to_vec())]).unwrap().arg0;
+        assert_eq!(arg0, "pr\u{fffd}g");
+    }
+
+    #[test]
+    fn test_argument_iterators() {
+        let parser = positionals(vec![CLPInput::new(INT, "a")]);
+        assert!(parser.parse(["prog", "1"]).is_ok());
+        assert!(parser.parse(vec!["prog".to_string(), "1".to_string()]).is_ok());
+        assert!(parser.parse(&["prog".to_string(), "1".to_string()][..]).is_ok());
+        assert!(parser.parse("prog 1".split(' ')).is_ok());
+        assert_eq!(message(parser.parse(Vec::<String>::new())), "Expected the program name as the first argument");
+        let result = parser.parse_without_arg0(["7"]).unwrap();
+        assert_eq!((result.arg0.as_str(), result.get::<i32>("a").unwrap()), ("", 7));
+        assert_eq!(message(parser.parse_without_arg0(Vec::<&str>::new())), "Didn't get input for INT(a)");
+    }
+
+    fn sample() -> CLPResult {
+        let parser = options(ArgsSettings::Args(vec![CLPInput::new(INT, "n"), CLPInput::new(STRING, "s"), CLPInput::new(VECFLOAT, "xs")]), vec![
+            ("-v", KwargTypes::Toggle([String::new()])),
+            ("--port", KwargTypes::Optional(CLPInput::new(UINT, "port"), messages("<port>")))
+        ]);
+        parser.parse(["prog", "-5", "text", "[0.5, 1]", "-v", "-v", "--port", "70000"]).unwrap()
+    }
+
+    #[test]
+    fn test_accessors() {
+        let result = sample();
+        assert_eq!(result.get_int("n").unwrap(), -5);
+        assert_eq!(result.get_str("s").unwrap(), "text");
+        assert_eq!(result.get_vec_float("xs").unwrap(), [0.5, 1.0]);
+        assert_eq!(result.count("v").unwrap(), 2);
+        assert!(result.flag("v").unwrap());
+        assert!(!result.flag("q").unwrap());
+        assert_eq!(result.get_int_or("missing", 3).unwrap(), 3);
+        assert_eq!(result.get_str_or("s", "x").unwrap(), "text");
+        assert!(matches!(result.get_int("missing"), Err(CLPErrorKind::MissingError(message)) if message == "no value for missing"));
+        assert!(matches!(result.get_uint("n"), Err(CLPErrorKind::TypeError(message)) if message == "n is Int, expected UInt"));
+        assert!(matches!(result.get_int_or("s", 3), Err(CLPErrorKind::TypeError(_))));
+        assert!(matches!(result.count("s"), Err(CLPErrorKind::TypeError(_))));
+    }
+
+    #[test]
+    fn test_take() {
+        let mut result = sample();
+        assert!(matches!(result.take_vec_int("xs"), Err(CLPErrorKind::TypeError(_))));
+        assert_eq!(result.take_vec_float("xs").unwrap(), [0.5, 1.0]);
+        assert!(matches!(result.take_vec_float("xs"), Err(CLPErrorKind::MissingError(_))));
+        assert_eq!(result.take_string("s").unwrap(), "text");
+        assert!(matches!(result.take("n"), Ok(CLPVar::Int(-5))));
+    }
+
+    #[test]
+    fn test_get() {
+        let parser = options(ArgsSettings::Args(vec![CLPInput::new(UINT, "big"), CLPInput::new(INT, "small"), CLPInput::new(VECSTRING, "files")]), vec![
+            ("-v", KwargTypes::Toggle([String::new()])),
+            ("--name", KwargTypes::Optional(CLPInput::new(STRING, "name"), messages("<name>")))
+        ]);
+        let result = parser.parse(["prog", "300", "-7", "[a.txt, b/c.txt]"]).unwrap();
+        assert_eq!(result.get::<u16>("big").unwrap(), 300);
+        assert_eq!(result.get::<i8>("small").unwrap(), -7);
+        assert!(matches!(result.get::<u8>("big"), Err(CLPErrorKind::TypeError(message)) if message == "big: 300 does not fit in u8"));
+        assert!(matches!(result.get::<u64>("small"), Err(CLPErrorKind::TypeError(message)) if message == "small: -7 does not fit in u64"));
+        assert!(matches!(result.get::<String>("big"), Err(CLPErrorKind::TypeError(message)) if message == "big: UInt can not be converted to String"));
+        assert_eq!(result.get::<Vec<std::path::PathBuf>>("files").unwrap(), [std::path::PathBuf::from("a.txt"), "b/c.txt".into()]);
+        assert_eq!(result.get::<Option<String>>("name").unwrap(), None);
+        assert!(!result.get::<bool>("v").unwrap());
+        assert!(matches!(result.get::<String>("name"), Err(CLPErrorKind::MissingError(_))));
+    }
+
+    #[test]
+    fn test_try_from() {
+        assert_eq!(u8::try_from(CLPVar::UInt(255)).unwrap(), 255);
+        assert!(matches!(u8::try_from(&CLPVar::UInt(256)), Err(CLPErrorKind::TypeError(message)) if message == "256 does not fit in u8"));
+        assert!(matches!(Vec::<u8>::try_from(CLPVar::VecUInt(vec![1, 256])), Err(CLPErrorKind::TypeError(message)) if message == "element 1: 256 does not fit in u8"));
+        assert_eq!(Option::<i32>::try_from(CLPVar::Int(-1)).unwrap(), Some(-1));
+        assert!(f32::try_from(CLPVar::Float(1e300)).is_err());
+    }
+
+    #[test]
+    fn test_sources() {
+        let parser = options(ArgsSettings::NONE, vec![
+            ("--port", KwargTypes::Optional(CLPInput::new(UINT, "port"), messages("<port>"))),
+            ("--host", KwargTypes::Optional(CLPInput::new(STRING, "host"), messages("<host>")))
+        ]);
+        let mut result = parser.parse(["prog", "--port", "80"]).unwrap();
+        assert!(!result.fill("port", CLPVar::UInt(8080), CLPSource::Env("PORT".to_string())));
+        assert!(result.fill("host", CLPVar::String("a".to_string()), CLPSource::Env("HOST".to_string())));
+        assert!(!result.fill("host", CLPVar::String("b".to_string()), CLPSource::Default));
+        assert_eq!(result.source("port"), Some(&CLPSource::CommandLine(1)));
+        assert_eq!(result.source("host"), Some(&CLPSource::Env("HOST".to_string())));
+        assert_eq!(result.get_str("host").unwrap(), "a");
+        assert!(matches!(result.insert("port", CLPVar::UInt(1), CLPSource::Default), Some(CLPVar::UInt(80))));
+        assert_eq!(result.source("port"), Some(&CLPSource::Default));
+        assert_eq!(result.source("missing"), None);
+    }
+
+    #[test]
+    fn test_occurrences() {
+        let parser = options(ArgsSettings::Args(vec![CLPInput::new(STRING, "file")]), vec![
+            ("-v", KwargTypes::Toggle([String::new()])),
+            ("-I", KwargTypes::Optional(CLPInput::new(STRING, "include"), messages("<dir>"))),
+            ("--include", KwargTypes::Optional(CLPInput::new(STRING, "include"), messages("<dir>")))
+        ]);
+        let result = parser.parse(["prog", "f", "-I", "a", "-v", "--include", "b", "-Ic"]).unwrap();
+        assert_eq!(result.indices_of("include"), [2, 5, 7]);
+        assert_eq!(result.indices_of("file"), [1]);
+        assert_eq!(result.indices_of("v"), [4]);
+        assert!(result.indices_of("missing").is_empty());
+        let occurrences = result.occurrences_in_order();
+        let keys: Vec<Option<&str>> = occurrences.iter().map(|occurrence| occurrence.key.as_deref()).collect();
+        assert_eq!(keys, [None, Some("-I"), Some("-v"), Some("--include"), Some("-I")]);
+        assert!(matches!(&occurrences[3].value, CLPVar::String(value) if value == "b"));
+        assert!(matches!(&occurrences[4].value, CLPVar::String(value) if value == "c"));
+        assert_eq!(result.get_str("include").unwrap(), "c");
+    }
+
+    #[test]
+    fn test_error_reasons() {
+        let parser = options(ArgsSettings::Args(vec![CLPInput::new(UINT, "n").constraint(CLPConstraint::UIntRange(None, Some(10)))]), vec![
+            ("--name", KwargTypes::Important(CLPInput::new(STRING, "name"), messages("<name>")))
+        ]);
+        let reason = |args: &[&str]| {
+            let error = parser.parse(["prog"].iter().chain(args)).unwrap_err();
+            (error.reason, error.exit_code())
+        };
+        assert_eq!(reason(&["--nmae", "x", "1"]), (CLPErrorReason::UnknownOption, 2));
+        assert_eq!(reason(&["1", "2", "--name", "x"]), (CLPErrorReason::UnexpectedArgument, 2));
+        assert_eq!(reason(&["--name", "x", "1"]), (CLPErrorReason::MisplacedOption, 2));
+        assert_eq!(reason(&["1", "--name"]), (CLPErrorReason::MissingValue, 2));
+        assert_eq!(reason(&["--name"]), (CLPErrorReason::MisplacedOption, 2));
+        assert_eq!(reason(&[]), (CLPErrorReason::MissingArgument, 2));
+        assert_eq!(reason(&["1"]), (CLPErrorReason::MissingOption, 2));
+        assert_eq!(reason(&["x", "--name", "x"]), (CLPErrorReason::InvalidValue, 2));
+        assert_eq!(reason(&["11", "--name", "x"]), (CLPErrorReason::ConstraintViolation, 2));
+        let result = parser.parse(["prog", "10", "--name", "x"]).unwrap();
+        let error = CLPError::from(result.get::<String>("n").unwrap_err());
+        assert_eq!((error.reason, error.exit_code()), (CLPErrorReason::TypeMismatch, 70));
+        let error = CLPError::from(result.get::<String>("missing").unwrap_err());
+        assert_eq!(error.reason, CLPErrorReason::MissingArgument);
+        assert_eq!(CLPError::from(CLPErrorKind::Error(String::new())).exit_code(), 1);
+    }
+
+    #[test]
+    fn test_parse_all() {
+        let parser = options(ArgsSettings::Args(vec![CLPInput::new(UINT, "a"), CLPInput::new(UINT, "b"), CLPInput::new(UINT, "c")]), vec![
+            ("-n", KwargTypes::Important(CLPInput::new(STRING, "name"), messages("<name>"))),
+            ("--name", KwargTypes::Important(CLPInput::new(STRING, "name"), messages("<name>"))),
+            ("--jobs", KwargTypes::Optional(CLPInput::new(UINT, "jobs"), messages("<jobs>")))
+        ]);
+        let errors = parser.parse_all(["prog", "1", "x", "--jobs", "y", "--bogus"]).unwrap_err();
+        let reasons: Vec<(CLPErrorReason, Option<usize>)> = errors.errors.iter().map(|error| (error.reason, error.index)).collect();
+        assert_eq!(reasons, [
+            (CLPErrorReason::InvalidValue, Some(2)),
+            (CLPErrorReason::MisplacedOption, Some(3)),
+            (CLPErrorReason::InvalidValue, Some(4)),
+            (CLPErrorReason::UnknownOption, Some(5)),
+            (CLPErrorReason::MissingArgument, None),
+            (CLPErrorReason::MissingOption, None)
+        ]);
+        assert_eq!(errors.errors[4].arg.as_deref(), Some("c"));
+        assert_eq!(errors.errors[5].arg.as_deref(), Some("--name"));
+        assert_eq!(errors.result.get_uint("a").unwrap(), 1);
+        assert!(errors.result.get_uint("jobs").is_err());
+        let errors = parser.parse_all(["prog", "1", "2", "3", "--name"]).unwrap_err();
+        let reasons: Vec<CLPErrorReason> = errors.errors.iter().map(|error| error.reason).collect();
+        assert_eq!(reasons, [CLPErrorReason::MissingValue]);
+        assert_eq!(errors.result.get_uint("c").unwrap(), 3);
+        assert!(parser.parse_all(["prog", "1", "2", "3", "-n", "x"]).is_ok());
+        assert_eq!(parser.parse_all(Vec::<String>::new()).unwrap_err().errors.len(), 1);
+    }
+
+    #[test]
+    fn test_mistyped_options() {
+        let parser = options(ArgsSettings::Args(vec![CLPInput::new(STRING, "file"), CLPInput::new(PATH, "out")]), vec![
+            ("--verbose", KwargTypes::Toggle([String::new()]))
+        ]);
+        assert_eq!(message(parser.parse(["prog", "--verbsoe", "f"])), "Unknown option --verbsoe, did you mean `--verbose`?");
+        assert_eq!(message(parser.parse(["prog", "f", "--verbsoe"])), "Unknown option --verbsoe, did you mean `--verbose`?");
+        assert_eq!(message(parser.parse(["prog", "--unrelated", "f"])), "Unknown option --unrelated");
+        assert_eq!(message(parser.parse(["prog", "-x", "f"])), "Unknown option -x");
+        assert_eq!(message(parser.parse(["prog", "-zzzz", "f"])), "Unknown option -zzzz");
+        assert_eq!(one(FLOAT, "-inf").unwrap().get::<f64>("a").unwrap(), f64::NEG_INFINITY);
+        let result = parser.parse(["prog", "-", "-1"]).unwrap();
+        assert_eq!((result.get_str("file").unwrap(), result.get_var("out").is_ok()), ("-", true));
+        assert_eq!(message(parser.parse(["prog", "f", "g", "h"])), "Unexpected input h");
+        let errors = parser.parse_all(["prog", "--verbsoe", "f", "g"]).unwrap_err();
+        assert_eq!(errors.errors.len(), 1);
+        assert_eq!(errors.errors[0].reason, CLPErrorReason::UnknownOption);
+        assert_eq!(errors.result.get_str("file").unwrap(), "f");
+        let parser = parser.with_suggest_distance(0);
+        assert_eq!(message(parser.parse(["prog", "--verbsoe", "f"])), "Unknown option --verbsoe");
+        let list = CommandLineParser::new(false, ArgsSettings::ALL(CLPInput::new(STRING, "f")), KwargSettings { keyvalues: HashMap::from([("--verbose".to_string(), KwargTypes::Toggle([String::new()]))]) });
+        assert_eq!(message(list.parse(["prog", "a", "--verbsoe"])), "Unknown option --verbsoe, did you mean `--verbose`?");
+    }
+
+    #[test]
+    fn test_to_json() {
+        assert_eq!(CLPVar::String("a\"b\\c\nd\u{1}".to_string()).to_json(), r#""a\"b\\c\nd\u0001""#);
+        assert_eq!(CLPVar::Float(f64::NAN).to_json(), "\"NaN\"");
+        assert_eq!(CLPVar::Float(f64::NEG_INFINITY).to_json(), "\"-Infinity\"");
+        assert_eq!(CLPVar::Float(1.0).to_json(), "1.0");
+        assert_eq!(CLPVar::Duration(Duration::from_millis(1500)).to_json(), "1.5");
+        assert_eq!(CLPVar::VecVecInt(vec![vec![1], vec![]]).to_json(), "[[1], []]");
+        assert_eq!(CLPVar::Map(HashMap::from([("b".to_string(), CLPVar::UInt(2)), ("a".to_string(), CLPVar::Flag(1))])).to_json(), r#"{"a": 1, "b": 2}"#);
+        let parser = CommandLineParser::new(true, ArgsSettings::Args(vec![CLPInput::new(IPADDR, "ip")]), KwargSettings { keyvalues: HashMap::new() });
+        let result = parser.parse(["prog", "::1", "x"]).unwrap();
+        assert_eq!(result.to_json(), r#"{"arg0": "prog", "default": ["x"], "args": {"ip": "::1"}, "sources": {"ip": "command line argument 1"}}"#);
+    }
+
+    #[test]
+    fn test_to_shell() {
+        let parser = CommandLineParser::new(true, ArgsSettings::Args(vec![CLPInput::new(STRING, "file-name"), CLPInput::new(VECINT, "1st"), CLPInput::new(MAP(Box::new(STRING)), "env")]), KwargSettings { keyvalues: HashMap::new() });
+        let result = parser.parse(["prog", "it's", "[1, 2]", "[a.b=$HOME]"]).unwrap();
+        assert_eq!(result.to_shell("my-"), concat!(
+            "MY_ARG0='prog'\n",
+            "MY_DEFAULT_COUNT=0\n",
+            "MY_1ST_COUNT=2\n",
+            "MY_1ST_0='1'\n",
+            "MY_1ST_1='2'\n",
+            "MY_ENV_KEYS='a.b'\n",
+            "MY_ENV_A_B='$HOME'\n",
+            "MY_FILE_NAME='it'\\''s'\n"
+        ));
+        assert_eq!(result.to_shell("").lines().nth(2), Some("_1ST_COUNT=2"));
+    }
+
+    #[cfg(feature = "derive")]
+    mod derive {
+        use crate::{CLPArgs, CLPErrorReason, CLPSource, CLPVar};
+
+        /// Copies files.
+        #[derive(crate::CLPArgs, Debug)]
+        struct Copy {
+            /// The file to read
+            input: String,
+            /// The file to write
+            output: std::path::PathBuf,
+            /// Print what is copied
+            #[arg(short)]
+            verbose: bool,
+            #[arg(long, short, default = "4")]
+            jobs: u8,
+            #[arg(long)]
+            mode: Option<String>,
+            #[arg(long, ty = RELTIME, default = "3d")]
+            since: crate::CLPDateTime,
+            #[arg(rest)]
+            rest: Vec<String>
+        }
+
+        #[test]
+        fn test_derive() {
+            let copy = Copy::parse(["cp", "a", "b", "-v", "--mode", "fast", "c", "d"]).unwrap();
+            assert_eq!((copy.input.as_str(), copy.output.to_str()), ("a", Some("b")));
+            assert!(copy.verbose);
+            assert_eq!(copy.jobs, 4);
+            assert_eq!(copy.mode.as_deref(), Some("fast"));
+            assert_eq!(copy.rest, ["c", "d"]);
+            let copy = Copy::parse(["cp", "a", "b", "-j", "8"]).unwrap();
+            assert_eq!((copy.jobs, copy.mode, copy.verbose), (8, None, false));
+        }
+
+        #[test]
+        fn test_derive_defaults() {
+            let mut result = Copy::parser().parse(["cp", "a", "b"]).unwrap();
+            assert_eq!(result.source("jobs"), None);
+            Copy::defaults(&mut result, std::time::SystemTime::now()).unwrap();
+            assert_eq!(result.source("jobs"), Some(&CLPSource::Default));
+            assert!(matches!(result.args["jobs"], CLPVar::UInt(4)));
+            assert_eq!(result.source("mode"), None);
+            let mut result = Copy::parser().parse(["cp", "a", "b", "-j", "8"]).unwrap();
+            Copy::defaults(&mut result, std::time::SystemTime::now()).unwrap();
+            assert_eq!(result.source("jobs"), Some(&CLPSource::CommandLine(3)));
+            assert_eq!(Copy::parse_from(&result).unwrap().jobs, 8);
+        }
+
+        #[test]
+        fn test_derive_now() {
+            let now = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1792324800);
+            assert_eq!(Copy::parse_at(["cp", "a", "b"], now).unwrap().since.to_string(), "2026-10-15T12:00:00Z");
+            assert_eq!(Copy::parse_at(["cp", "a", "b", "--since", "1h"], now).unwrap().since.to_string(), "2026-10-18T11:00:00Z");
+        }
+
+        #[test]
+        fn test_derive_errors() {
+            assert_eq!(Copy::parse(["cp", "a"]).unwrap_err().reason, CLPErrorReason::MissingArgument);
+            assert_eq!(Copy::parse(["cp", "-v", "a", "b"]).unwrap_err().reason, CLPErrorReason::MisplacedOption);
+            assert_eq!(Copy::parse(["cp", "a", "b", "-j", "x"]).unwrap_err().reason, CLPErrorReason::InvalidValue);
+        }
+
+        #[test]
+        fn test_derive_help() {
+            let help = Copy::help();
+            assert!(help.starts_with("Copies files.\n\nUsage: <input> <output> [OPTIONS] [rest]...\n"), "{}", help);
+            assert!(help.contains("  <input>   The file to read\n"), "{}", help);
+            assert!(help.contains("-j, --jobs <jobs>  [default: 4]\n"), "{}", help);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    mod serde {
+        use std::collections::HashMap;
+        use serde::Deserialize;
+        use crate::{from_result, from_var, CLPErrorKind, CLPVar, KwargTypes, CLPInput, ArgsSettings};
+        use crate::ArgTypes::{UINT, STRING, VECFLOAT, DURATION, MAP};
+        use super::{messages, options};
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(rename_all = "lowercase")]
+        enum Mode {
+            Fast,
+            Slow
+        }
+
+        #[derive(Deserialize, Debug)]
+        struct Config {
+            port: u16,
+            mode: Mode,
+            weights: Vec<f32>,
+            timeout: Option<std::time::Duration>,
+            verbose: bool,
+            labels: HashMap<String, String>
+        }
+
+        fn config(args: &[&str]) -> Result<Config, CLPErrorKind> {
+            let parser = options(ArgsSettings::Args(vec![CLPInput::new(UINT, "port"), CLPInput::new(STRING, "mode"), CLPInput::new(VECFLOAT, "weights")]), vec![
+                ("--timeout", KwargTypes::Optional(CLPInput::new(DURATION, "timeout"), messages("<time>"))),
+                ("--label", KwargTypes::Optional(CLPInput::new(MAP(Box::new(STRING)), "labels"), messages("<labels>"))),
+                ("--verbose", KwargTypes::Toggle([String::new()]))
+            ]);
+            from_result(&parser.parse(["prog"].iter().chain(args)).unwrap())
+        }
+
+        #[test]
+        fn test_from_result() {
+            let config = config(&["8080", "fast", "[1, 0.5]", "--timeout", "1.5s", "--verbose", "--label", "[a=x]"]).unwrap();
+            assert_eq!((config.port, config.mode), (8080, Mode::Fast));
+            assert_eq!(config.weights, [1.0, 0.5]);
+            assert_eq!(config.timeout, Some(std::time::Duration::from_millis(1500)));
+            assert!(config.verbose);
+            assert_eq!(config.labels["a"], "x");
+        }
+
+        #[test]
+        fn test_from_result_errors() {
+            assert!(matches!(config(&["70000", "fast", "[]", "--label", "[]"]), Err(CLPErrorKind::TypeError(message)) if message.starts_with("port: ")));
+            assert!(matches!(config(&["1", "medium", "[]", "--label", "[]"]), Err(CLPErrorKind::TypeError(message)) if message.starts_with("mode: ")));
+            assert!(matches!(config(&["1", "slow", "[]", "--verbose"]), Err(CLPErrorKind::MissingError(message)) if message == "no value for labels"));
+        }
+
+        #[derive(Deserialize, Debug)]
+        struct Flags {
+            #[serde(default)]
+            verbose: bool,
+            #[serde(rename = "dry-run")]
+            dry_run: bool,
+            #[serde(default)]
+            jobs: u8,
+            #[serde(default = "name")]
+            name: String
+        }
+
+        fn name() -> String {
+            "out".to_string()
+        }
+
+        #[test]
+        fn test_from_result_defaults() {
+            let parser = options(ArgsSettings::NONE, vec![
+                ("--verbose", KwargTypes::Toggle([String::new()])),
+                ("--dry-run", KwargTypes::Toggle([String::new()])),
+                ("--jobs", KwargTypes::Optional(CLPInput::new(UINT, "jobs"), messages("<jobs>")))
+            ]);
+            let flags: Flags = from_result(&parser.parse(["prog", "--dry-run"]).unwrap()).unwrap();
+            assert!(!flags.verbose && flags.dry_run);
+            assert_eq!((flags.jobs, flags.name.as_str()), (0, "out"));
+            let flags: Flags = from_result(&parser.parse(["prog", "--dry-run", "--verbose", "--jobs", "4"]).unwrap()).unwrap();
+            assert!(flags.verbose);
+            assert_eq!(flags.jobs, 4);
+            // a toggle that was not given has no value without #[serde(default)]
+            assert!(matches!(from_result::<Flags>(&parser.parse(["prog"]).unwrap()), Err(CLPErrorKind::MissingError(message)) if message == "no value for dry-run"));
+        }
+
+        #[test]
+        fn test_from_var() {
+            assert_eq!(from_var::<Vec<u8>>(&CLPVar::VecUInt(vec![1, 2])).unwrap(), [1, 2]);
+            assert_eq!(from_var::<Mode>(&CLPVar::String("slow".to_string())).unwrap(), Mode::Slow);
+            assert_eq!(from_var::<(u64, u32)>(&CLPVar::Duration(std::time::Duration::new(2, 5))).unwrap(), (2, 5));
+            assert!(from_var::<u8>(&CLPVar::UInt(256)).is_err());
+        }
+    }
+}
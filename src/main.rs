@@ -1,44 +1,34 @@
 use std::collections::HashMap;
-use clparser::{CommandLineParser, KwargSettings, ArgsSettings, KwargTypes, CLPErrorKind, CLPInput};
-use clparser::ArgTypes::{STRING, VECSTRING, VECFLOAT};
+use clparser::{CommandLineParser, KwargSettings, ArgsSettings, KwargTypes, CLPInput, CLPConstraint};
+use clparser::ArgTypes::{INT, STRING, VECSTRING, VECFLOAT};
 
 fn main() {
     let mut map = HashMap::<String, KwargTypes>::new();
-    map.insert("--int".to_string(), KwargTypes::Important([
-        "Integer".to_string(),
-        "<int>".to_string(),
-        "unexpected value {} expected an <int>".to_string()
-    ]));
+    map.insert("--int".to_string(), KwargTypes::Important(
+        CLPInput::new(INT, "int").constraint(CLPConstraint::IntRange(Some(1), Some(256))),
+        [
+            "Integer".to_string(),
+            "<int>".to_string(),
+            "unexpected value {} expected an <int>".to_string()
+        ]
+    ));
     let parser = CommandLineParser::new(
         false,
         ArgsSettings::Args(vec![
-            CLPInput {
-                object_type: STRING,
-                name: "int".to_string()
-            },
-            CLPInput {
-                object_type: VECSTRING,
-                name: "int".to_string()
-            },
-            CLPInput {
-                object_type: VECFLOAT,
-                name: "int".to_string()
-            }
+            CLPInput::new(STRING, "string"),
+            CLPInput::new(VECSTRING, "strings"),
+            CLPInput::new(VECFLOAT, "floats")
+                .constraint(CLPConstraint::FloatRange(Some(0.0), Some(1.0)))
         ]),
         KwargSettings {
             keyvalues: map
         });
-    let args: Vec<String> = std::env::args().collect();
-    let variables = parser.parse(&args);
+    let variables = parser.parse_env();
 
     match variables {
         Err(err) => {
-            match err {
-                CLPErrorKind::Error(value) => println!("{}", value),
-                CLPErrorKind::ArgsError(value) => println!("{}", value),
-                CLPErrorKind::KwargError(value) => println!("{}", value),
-                CLPErrorKind::ParseError(value) => println!("{}", value),
-            }
+            println!("{}", err);
+            std::process::exit(err.exit_code());
         },
         Ok(value) => {
             println!("{:#?}", value);
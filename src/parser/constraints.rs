@@ -0,0 +1,143 @@
+use super::types::{CLPConstraint, CLPErrorKind, CLPVar};
+
+fn in_range<T: PartialOrd>(value: &T, min: &Option<T>, max: &Option<T>) -> bool {
+    min.as_ref().is_none_or(|min| value >= min) && max.as_ref().is_none_or(|max| value <= max)
+}
+
+fn int_in_range(value: u128, min: &Option<i128>, max: &Option<i128>) -> bool {
+    match i128::try_from(value) {
+        Ok(value) => in_range(&value, min, max),
+        Err(_) => max.is_none(),
+    }
+}
+
+fn out_of_range<T: std::fmt::Display>(constraint: &CLPConstraint, name: &str, value: T, index: Option<String>) -> CLPErrorKind {
+    let value = match index {
+        Some(index) => format!("{} at index {} of {}", value, index, name),
+        None => format!("{} for {}", value, name),
+    };
+    match constraint {
+        CLPConstraint::Finite => CLPErrorKind::ConstraintError(format!("{} is not a finite number", value)),
+        _ => CLPErrorKind::ConstraintError(format!("{} is outside of the allowed interval {:?}", value, constraint)),
+    }
+}
+
+fn check_all<T: std::fmt::Display>(constraint: &CLPConstraint, name: &str, values: &[T], ok: impl Fn(&T) -> bool) -> Option<CLPErrorKind> {
+    values.iter()
+        .position(|value| !ok(value))
+        .map(|index| out_of_range(constraint, name, &values[index], Some(index.to_string())))
+}
+
+fn check_rows<T: std::fmt::Display>(constraint: &CLPConstraint, name: &str, rows: &[Vec<T>], ok: impl Fn(&T) -> bool) -> Option<CLPErrorKind> {
+    rows.iter().enumerate().find_map(|(row, values)| {
+        values.iter()
+            .position(|value| !ok(value))
+            .map(|column| out_of_range(constraint, name, &values[column], Some(format!("[{}][{}]", row, column))))
+    })
+}
+
+fn check_shape(constraint: &CLPConstraint, name: &str, columns: &[usize]) -> Option<CLPErrorKind> {
+    match constraint {
+        CLPConstraint::Shape(Some(rows), _) if columns.len() != *rows => {
+            Some(CLPErrorKind::ConstraintError(format!("{} has {} rows, expected {}", name, columns.len(), rows)))
+        },
+        CLPConstraint::Shape(_, Some(expected)) => columns.iter()
+            .position(|count| count != expected)
+            .map(|row| CLPErrorKind::ConstraintError(format!("row {} of {} has {} columns, expected {}", row, name, columns[row], expected))),
+        CLPConstraint::Rectangular => columns.iter()
+            .position(|count| *count != columns[0])
+            .map(|row| CLPErrorKind::ConstraintError(format!("row {} of {} has {} columns but row 0 has {}", row, name, columns[row], columns[0]))),
+        _ => None,
+    }
+}
+
+fn check_order<T: PartialOrd + std::fmt::Debug>(constraint: &CLPConstraint, name: &str, values: &[T]) -> Option<CLPErrorKind> {
+    match constraint {
+        CLPConstraint::Unique => values.iter().enumerate().find_map(|(index, value)| {
+            values[..index].iter()
+                .position(|other| other == value)
+                .map(|first| CLPErrorKind::ConstraintError(format!("{:?} at index {} of {} repeats index {}", value, index, name, first)))
+        }),
+        CLPConstraint::Ascending => (1..values.len())
+            .find(|index| values[*index] < values[index - 1])
+            .map(|index| CLPErrorKind::ConstraintError(format!("{:?} at index {} of {} is smaller than {:?}, expected ascending order", values[index], index, name, values[index - 1]))),
+        CLPConstraint::Descending => (1..values.len())
+            .find(|index| values[*index] > values[index - 1])
+            .map(|index| CLPErrorKind::ConstraintError(format!("{:?} at index {} of {} is larger than {:?}, expected descending order", values[index], index, name, values[index - 1]))),
+        _ => None,
+    }
+}
+
+/// Checks the length, uniqueness and order constraints, `None` if `var` is
+/// not a vector or `constraint` is none of those.
+fn check_sequence(constraint: &CLPConstraint, name: &str, var: &CLPVar) -> Option<Option<CLPErrorKind>> {
+    if let CLPConstraint::Length(min, max) = constraint {
+        let len = match var {
+            CLPVar::VecInt(v) => v.len(),
+            CLPVar::VecUInt(v) => v.len(),
+            CLPVar::VecFloat(v) => v.len(),
+            CLPVar::VecString(v) => v.len(),
+            CLPVar::VecVecInt(v) => v.len(),
+            CLPVar::VecVecUInt(v) => v.len(),
+            CLPVar::VecVecFloat(v) => v.len(),
+            CLPVar::VecIpAddr(v) => v.len(),
+            CLPVar::VecSocketAddr(v) => v.len(),
+            CLPVar::Map(v) => v.len(),
+            _ => return None,
+        };
+        if in_range(&len, min, max) {
+            return Some(None);
+        }
+        return Some(Some(CLPErrorKind::ConstraintError(match (min, max) {
+            (Some(min), Some(max)) if min == max => format!("{} has {} elements, expected {}", name, len, min),
+            _ => format!("{} has {} elements, expected {:?}", name, len, constraint),
+        })));
+    }
+    if !matches!(constraint, CLPConstraint::Unique | CLPConstraint::Ascending | CLPConstraint::Descending) {
+        return None;
+    }
+    match var {
+        CLPVar::VecInt(v) => Some(check_order(constraint, name, v)),
+        CLPVar::VecUInt(v) => Some(check_order(constraint, name, v)),
+        CLPVar::VecFloat(v) => Some(check_order(constraint, name, v)),
+        CLPVar::VecString(v) => Some(check_order(constraint, name, v)),
+        CLPVar::VecVecInt(v) => Some(check_order(constraint, name, v)),
+        CLPVar::VecVecUInt(v) => Some(check_order(constraint, name, v)),
+        CLPVar::VecVecFloat(v) => Some(check_order(constraint, name, v)),
+        CLPVar::VecIpAddr(v) => Some(check_order(constraint, name, v)),
+        CLPVar::VecSocketAddr(v) => Some(check_order(constraint, name, v)),
+        _ => None,
+    }
+}
+
+/// Checks a parsed value against a constraint, `name` is only used for the error message.
+pub(crate) fn check(constraint: &CLPConstraint, name: &str, var: &CLPVar) -> Option<CLPErrorKind> {
+    if let Some(result) = check_sequence(constraint, name, var) {
+        return result;
+    }
+    let check_one = |ok: bool, value: &dyn std::fmt::Display| {
+        if ok { None } else { Some(out_of_range(constraint, name, value, None)) }
+    };
+    match (constraint, var) {
+        (CLPConstraint::IntRange(min, max), CLPVar::Int(v)) => check_one(in_range(v, min, max), v),
+        (CLPConstraint::IntRange(min, max), CLPVar::UInt(v)) => check_one(int_in_range(*v, min, max), v),
+        (CLPConstraint::IntRange(min, max), CLPVar::VecInt(v)) => check_all(constraint, name, v, |v| in_range(v, min, max)),
+        (CLPConstraint::IntRange(min, max), CLPVar::VecUInt(v)) => check_all(constraint, name, v, |v| int_in_range(*v, min, max)),
+        (CLPConstraint::IntRange(min, max), CLPVar::VecVecInt(v)) => check_rows(constraint, name, v, |v| in_range(v, min, max)),
+        (CLPConstraint::IntRange(min, max), CLPVar::VecVecUInt(v)) => check_rows(constraint, name, v, |v| int_in_range(*v, min, max)),
+        (CLPConstraint::UIntRange(min, max), CLPVar::UInt(v)) => check_one(in_range(v, min, max), v),
+        (CLPConstraint::UIntRange(min, max), CLPVar::VecUInt(v)) => check_all(constraint, name, v, |v| in_range(v, min, max)),
+        (CLPConstraint::UIntRange(min, max), CLPVar::VecVecUInt(v)) => check_rows(constraint, name, v, |v| in_range(v, min, max)),
+        (CLPConstraint::FloatRange(min, max), CLPVar::Float(v)) => check_one(in_range(v, min, max), v),
+        (CLPConstraint::FloatRange(min, max), CLPVar::VecFloat(v)) => check_all(constraint, name, v, |v| in_range(v, min, max)),
+        (CLPConstraint::FloatRange(min, max), CLPVar::VecVecFloat(v)) => check_rows(constraint, name, v, |v| in_range(v, min, max)),
+        (CLPConstraint::Finite, CLPVar::Float(v)) => check_one(v.is_finite(), v),
+        (CLPConstraint::Finite, CLPVar::VecFloat(v)) => check_all(constraint, name, v, |v| v.is_finite()),
+        (CLPConstraint::Finite, CLPVar::VecVecFloat(v)) => check_rows(constraint, name, v, |v| v.is_finite()),
+        (CLPConstraint::Shape(..) | CLPConstraint::Rectangular, CLPVar::VecVecInt(v)) => check_shape(constraint, name, &v.iter().map(Vec::len).collect::<Vec<_>>()),
+        (CLPConstraint::Shape(..) | CLPConstraint::Rectangular, CLPVar::VecVecUInt(v)) => check_shape(constraint, name, &v.iter().map(Vec::len).collect::<Vec<_>>()),
+        (CLPConstraint::Shape(..) | CLPConstraint::Rectangular, CLPVar::VecVecFloat(v)) => check_shape(constraint, name, &v.iter().map(Vec::len).collect::<Vec<_>>()),
+        (CLPConstraint::Custom(check), var) => check(var).err().map(|message| CLPErrorKind::ConstraintError(format!("{} for {}", message, name))),
+        (constraint, var) => Some(CLPErrorKind::ConstraintError(format!("constraint {:?} can not be applied to {:?} of {}", constraint, var, name))),
+    }
+}
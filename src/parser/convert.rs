@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
+use super::types::{CLPDate, CLPDateTime, CLPErrorKind, CLPResult, CLPVar};
+
+/// Native types a `CLPVar` converts into, see `CLPResult::get`. `from_var`
+/// returns the reason when the value does not fit.
+pub trait CLPValue: Sized {
+    fn from_var(var: CLPVar) -> Result<Self, String>;
+
+    /// The value of a missing argument, `None` reports it as missing.
+    fn missing() -> Option<Self> {
+        None
+    }
+}
+
+/// The type name of `T` without module paths, `Vec<String>` rather than
+/// `alloc::vec::Vec<alloc::string::String>`.
+fn short_name<T>() -> String {
+    let mut name = String::new();
+    let mut segment = 0;
+    let mut chars = std::any::type_name::<T>().chars().peekable();
+    while let Some(char) = chars.next() {
+        if char == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            name.truncate(segment);
+            continue;
+        }
+        name.push(char);
+        if !(char.is_alphanumeric() || char == '_') {
+            segment = name.len();
+        }
+    }
+    name
+}
+
+fn mismatch<T>(var: &CLPVar) -> String {
+    format!("{} can not be converted to {}", var.type_name(), short_name::<T>())
+}
+
+/// The elements of a vector or tuple value.
+pub(crate) fn into_elements(var: CLPVar) -> Result<Vec<CLPVar>, CLPVar> {
+    match var {
+        CLPVar::VecInt(v) => Ok(v.into_iter().map(CLPVar::Int).collect()),
+        CLPVar::VecUInt(v) => Ok(v.into_iter().map(CLPVar::UInt).collect()),
+        CLPVar::VecFloat(v) => Ok(v.into_iter().map(CLPVar::Float).collect()),
+        CLPVar::VecString(v) => Ok(v.into_iter().map(CLPVar::String).collect()),
+        CLPVar::VecVecInt(v) => Ok(v.into_iter().map(CLPVar::VecInt).collect()),
+        CLPVar::VecVecUInt(v) => Ok(v.into_iter().map(CLPVar::VecUInt).collect()),
+        CLPVar::VecVecFloat(v) => Ok(v.into_iter().map(CLPVar::VecFloat).collect()),
+        CLPVar::VecIpAddr(v) => Ok(v.into_iter().map(CLPVar::IpAddr).collect()),
+        CLPVar::VecSocketAddr(v) => Ok(v.into_iter().map(CLPVar::SocketAddr).collect()),
+        CLPVar::Tuple(v) => Ok(v),
+        var => Err(var),
+    }
+}
+
+// integers are stored as i128 or u128 and narrowed with a range check
+macro_rules! integer_value {
+    ($($int:ty),*) => {$(
+        impl CLPValue for $int {
+            fn from_var(var: CLPVar) -> Result<Self, String> {
+                let (fits, value) = match &var {
+                    CLPVar::Int(v) => (<$int>::try_from(*v).ok(), v.to_string()),
+                    CLPVar::UInt(v) => (<$int>::try_from(*v).ok(), v.to_string()),
+                    CLPVar::Size(v) => (<$int>::try_from(*v).ok(), v.to_string()),
+                    CLPVar::Flag(v) => (<$int>::try_from(*v).ok(), v.to_string()),
+                    var => return Err(mismatch::<$int>(var)),
+                };
+                fits.ok_or_else(|| format!("{} does not fit in {}", value, stringify!($int)))
+            }
+        }
+    )*};
+}
+
+integer_value!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl CLPValue for f64 {
+    fn from_var(var: CLPVar) -> Result<Self, String> {
+        match var {
+            CLPVar::Float(v) => Ok(v),
+            var => Err(mismatch::<f64>(&var)),
+        }
+    }
+}
+
+impl CLPValue for f32 {
+    fn from_var(var: CLPVar) -> Result<Self, String> {
+        match var {
+            CLPVar::Float(v) if v.is_finite() && v.abs() > f32::MAX as f64 => Err(format!("{} does not fit in f32", v)),
+            CLPVar::Float(v) => Ok(v as f32),
+            var => Err(mismatch::<f32>(&var)),
+        }
+    }
+}
+
+impl CLPValue for bool {
+    fn from_var(var: CLPVar) -> Result<Self, String> {
+        match var {
+            CLPVar::Flag(count) => Ok(count > 0),
+            var => Err(mismatch::<bool>(&var)),
+        }
+    }
+
+    /// A toggle that was not given is `false`.
+    fn missing() -> Option<Self> {
+        Some(false)
+    }
+}
+
+impl CLPValue for String {
+    fn from_var(var: CLPVar) -> Result<Self, String> {
+        match var {
+            CLPVar::String(v) => Ok(v),
+            var => Err(mismatch::<String>(&var)),
+        }
+    }
+}
+
+impl CLPValue for PathBuf {
+    fn from_var(var: CLPVar) -> Result<Self, String> {
+        match var {
+            CLPVar::Path(v) => Ok(v),
+            CLPVar::String(v) => Ok(v.into()),
+            CLPVar::OsString(v) => Ok(v.into()),
+            var => Err(mismatch::<PathBuf>(&var)),
+        }
+    }
+}
+
+impl CLPValue for OsString {
+    fn from_var(var: CLPVar) -> Result<Self, String> {
+        match var {
+            CLPVar::OsString(v) => Ok(v),
+            CLPVar::String(v) => Ok(v.into()),
+            CLPVar::Path(v) => Ok(v.into()),
+            var => Err(mismatch::<OsString>(&var)),
+        }
+    }
+}
+
+impl CLPValue for Duration {
+    fn from_var(var: CLPVar) -> Result<Self, String> {
+        match var {
+            CLPVar::Duration(v) => Ok(v),
+            var => Err(mismatch::<Duration>(&var)),
+        }
+    }
+}
+
+impl CLPValue for IpAddr {
+    fn from_var(var: CLPVar) -> Result<Self, String> {
+        match var {
+            CLPVar::IpAddr(v) => Ok(v),
+            var => Err(mismatch::<IpAddr>(&var)),
+        }
+    }
+}
+
+impl CLPValue for SocketAddr {
+    fn from_var(var: CLPVar) -> Result<Self, String> {
+        match var {
+            CLPVar::SocketAddr(v) => Ok(v),
+            var => Err(mismatch::<SocketAddr>(&var)),
+        }
+    }
+}
+
+impl CLPValue for CLPDate {
+    fn from_var(var: CLPVar) -> Result<Self, String> {
+        match var {
+            CLPVar::Date(v) => Ok(v),
+            var => Err(mismatch::<CLPDate>(&var)),
+        }
+    }
+}
+
+impl CLPValue for CLPDateTime {
+    fn from_var(var: CLPVar) -> Result<Self, String> {
+        match var {
+            CLPVar::DateTime(v) => Ok(v),
+            var => Err(mismatch::<CLPDateTime>(&var)),
+        }
+    }
+}
+
+impl<T: CLPValue> CLPValue for Vec<T> {
+    fn from_var(var: CLPVar) -> Result<Self, String> {
+        into_elements(var)
+            .map_err(|var| mismatch::<Vec<T>>(&var))?
+            .into_iter()
+            .enumerate()
+            .map(|(index, element)| T::from_var(element).map_err(|err| format!("element {}: {}", index, err)))
+            .collect()
+    }
+}
+
+impl<T: CLPValue> CLPValue for HashMap<String, T> {
+    fn from_var(var: CLPVar) -> Result<Self, String> {
+        match var {
+            CLPVar::Map(map) => map.into_iter()
+                .map(|(key, value)| T::from_var(value).map(|value| (key.clone(), value)).map_err(|err| format!("key {:?}: {}", key, err)))
+                .collect(),
+            var => Err(mismatch::<HashMap<String, T>>(&var)),
+        }
+    }
+}
+
+impl<T: CLPValue> CLPValue for Option<T> {
+    fn from_var(var: CLPVar) -> Result<Self, String> {
+        T::from_var(var).map(Some)
+    }
+
+    fn missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+macro_rules! try_from_var {
+    ($($value:ty),*) => {$(
+        impl TryFrom<CLPVar> for $value {
+            type Error = CLPErrorKind;
+
+            fn try_from(var: CLPVar) -> Result<Self, Self::Error> {
+                <$value as CLPValue>::from_var(var).map_err(CLPErrorKind::TypeError)
+            }
+        }
+
+        impl TryFrom<&CLPVar> for $value {
+            type Error = CLPErrorKind;
+
+            fn try_from(var: &CLPVar) -> Result<Self, Self::Error> {
+                <$value as CLPValue>::from_var(var.clone()).map_err(CLPErrorKind::TypeError)
+            }
+        }
+    )*};
+}
+
+try_from_var!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, bool);
+try_from_var!(String, PathBuf, OsString, Duration, IpAddr, SocketAddr, CLPDate, CLPDateTime);
+
+impl<T: CLPValue> TryFrom<CLPVar> for Vec<T> {
+    type Error = CLPErrorKind;
+
+    fn try_from(var: CLPVar) -> Result<Self, Self::Error> {
+        <Vec<T> as CLPValue>::from_var(var).map_err(CLPErrorKind::TypeError)
+    }
+}
+
+impl<T: CLPValue> TryFrom<&CLPVar> for Vec<T> {
+    type Error = CLPErrorKind;
+
+    fn try_from(var: &CLPVar) -> Result<Self, Self::Error> {
+        <Vec<T> as CLPValue>::from_var(var.clone()).map_err(CLPErrorKind::TypeError)
+    }
+}
+
+impl CLPResult {
+    /// Converts the value of `name` into `T`, as in
+    /// `let port: u16 = result.get("port")?`. Integers are narrowed with a
+    /// range check, an `Option` is `None` when the value is missing.
+    pub fn get<T: CLPValue>(&self, name: &str) -> Result<T, CLPErrorKind> {
+        match self.args.get(name) {
+            Some(var) => T::from_var(var.clone()).map_err(|err| CLPErrorKind::TypeError(format!("{}: {}", name, err))),
+            None => T::missing().ok_or_else(|| CLPErrorKind::MissingError(format!("no value for {}", name))),
+        }
+    }
+}
+
+impl<T: CLPValue> TryFrom<CLPVar> for Option<T> {
+    type Error = CLPErrorKind;
+
+    fn try_from(var: CLPVar) -> Result<Self, Self::Error> {
+        <Option<T> as CLPValue>::from_var(var).map_err(CLPErrorKind::TypeError)
+    }
+}
+
+impl<T: CLPValue> TryFrom<&CLPVar> for Option<T> {
+    type Error = CLPErrorKind;
+
+    fn try_from(var: &CLPVar) -> Result<Self, Self::Error> {
+        <Option<T> as CLPValue>::from_var(var.clone()).map_err(CLPErrorKind::TypeError)
+    }
+}
@@ -0,0 +1,181 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use super::types::{CLPDate, CLPDateTime};
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// The date `days` after 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parses exactly `len` ASCII digits.
+fn digits(arg: &str, len: usize) -> Option<u32> {
+    if arg.len() == len && arg.bytes().all(|byte| byte.is_ascii_digit()) {
+        arg.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Parses `YYYY-MM-DD`.
+pub(crate) fn parse_date(arg: &str) -> Result<CLPDate, String> {
+    let mut parts = arg.splitn(3, '-');
+    let (year, month, day) = match (parts.next().and_then(|year| digits(year, 4)), parts.next().and_then(|month| digits(month, 2)), parts.next().and_then(|day| digits(day, 2))) {
+        (Some(year), Some(month), Some(day)) => (year as i32, month as u8, day as u8),
+        _ => return Err("expected YYYY-MM-DD".to_string()),
+    };
+    if !(1..=12).contains(&month) {
+        return Err(format!("month {} is out of range", month));
+    }
+    if day == 0 || day > days_in_month(year, month) {
+        return Err(format!("day {} is out of range for {:04}-{:02}", day, year, month));
+    }
+    Ok(CLPDate { year, month, day })
+}
+
+/// Parses a UTC offset `Z`, `+HH:MM`, `+HHMM` or `+HH` into seconds.
+fn parse_offset(arg: &str) -> Result<i32, String> {
+    if arg.eq_ignore_ascii_case("z") {
+        return Ok(0);
+    }
+    let (sign, rest) = match arg.split_at_checked(1) {
+        Some(("+", rest)) => (1, rest),
+        Some(("-", rest)) => (-1, rest),
+        _ => return Err(format!("invalid UTC offset {:?}", arg)),
+    };
+    // the slicing below needs ASCII
+    if !rest.is_ascii() {
+        return Err(format!("invalid UTC offset {:?}", arg));
+    }
+    let (hours, minutes) = match rest.len() {
+        2 => (digits(rest, 2), Some(0)),
+        4 => (digits(&rest[..2], 2), digits(&rest[2..], 2)),
+        5 if &rest[2..3] == ":" => (digits(&rest[..2], 2), digits(&rest[3..], 2)),
+        _ => (None, None),
+    };
+    match (hours, minutes) {
+        (Some(hours), Some(minutes)) if hours <= 23 && minutes <= 59 => Ok(sign * (hours * 3600 + minutes * 60) as i32),
+        _ => Err(format!("invalid UTC offset {:?}", arg)),
+    }
+}
+
+/// Parses `YYYY-MM-DDTHH:MM[:SS[.fraction]][offset]`, a space may stand in for
+/// the `T`.
+pub(crate) fn parse_datetime(arg: &str) -> Result<CLPDateTime, String> {
+    let (date, time) = match arg.split_once(['T', 't', ' ']) {
+        Some(parts) => parts,
+        None => return Err("expected YYYY-MM-DDTHH:MM:SS".to_string()),
+    };
+    let date = parse_date(date)?;
+    let zone_start = time.find(['Z', 'z', '+', '-']).unwrap_or(time.len());
+    let (time, offset) = match &time[zone_start..] {
+        "" => (time, None),
+        zone => (&time[..zone_start], Some(parse_offset(zone)?)),
+    };
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut parts = time.split(':');
+    let (hour, minute, second) = match (parts.next().and_then(|hour| digits(hour, 2)), parts.next().and_then(|minute| digits(minute, 2)), parts.next().map(|second| digits(second, 2)), parts.next()) {
+        (Some(hour), Some(minute), None, None) if fraction.is_empty() => (hour, minute, 0),
+        (Some(hour), Some(minute), Some(Some(second)), None) => (hour, minute, second),
+        _ => return Err("expected HH:MM:SS".to_string()),
+    };
+    if hour > 23 || minute > 59 || second > 59 {
+        return Err(format!("time {:02}:{:02}:{:02} is out of range", hour, minute, second));
+    }
+    if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return Err("expected digits after .".to_string());
+    }
+    // digits past nanoseconds are dropped
+    let fraction = &fraction[..fraction.len().min(9)];
+    let nanosecond = match fraction {
+        "" => 0,
+        fraction => digits(fraction, fraction.len()).ok_or("expected digits after .")? * 10u32.pow(9 - fraction.len() as u32),
+    };
+    Ok(CLPDateTime {
+        date,
+        hour: hour as u8,
+        minute: minute as u8,
+        second: second as u8,
+        nanosecond,
+        offset
+    })
+}
+
+/// The UTC date and time of `nanos` nanoseconds since the epoch.
+fn from_unix_nanos(nanos: i128) -> CLPDateTime {
+    let secs = nanos.div_euclid(1_000_000_000) as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86400));
+    let seconds_of_day = secs.rem_euclid(86400);
+    CLPDateTime {
+        date: CLPDate { year: year as i32, month: month as u8, day: day as u8 },
+        hour: (seconds_of_day / 3600) as u8,
+        minute: (seconds_of_day / 60 % 60) as u8,
+        second: (seconds_of_day % 60) as u8,
+        nanosecond: nanos.rem_euclid(1_000_000_000) as u32,
+        offset: Some(0)
+    }
+}
+
+fn unix_nanos(time: SystemTime) -> i128 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_nanos() as i128,
+        Err(err) => -(err.duration().as_nanos() as i128),
+    }
+}
+
+/// Parses a date, a date and time, `now` or a time relative to `now`. Relative
+/// times are durations such as `3d` or `1h30m`, they lie in the past unless
+/// prefixed with `+`, a `-` prefix or an ` ago` suffix is also accepted.
+pub(crate) fn parse_relative(arg: &str, now: SystemTime) -> Result<CLPDateTime, String> {
+    let now = unix_nanos(now);
+    if arg == "now" {
+        return Ok(from_unix_nanos(now));
+    }
+    if arg.get(..4).is_some_and(|year| year.bytes().all(|byte| byte.is_ascii_digit())) && arg.get(4..5) == Some("-") {
+        return match parse_date(arg) {
+            Ok(date) => Ok(CLPDateTime { date, hour: 0, minute: 0, second: 0, nanosecond: 0, offset: None }),
+            Err(err) if arg.len() == 10 => Err(err),
+            Err(_) => parse_datetime(arg),
+        };
+    }
+    let (future, duration) = match arg.strip_prefix('+') {
+        Some(duration) => (true, duration),
+        None => (false, arg.strip_prefix('-').or_else(|| arg.strip_suffix(" ago")).unwrap_or(arg)),
+    };
+    let duration = super::duration::parse_duration(duration, None)
+        .map_err(|_| "expected a date, a date and time or a relative time like 3d".to_string())?;
+    let offset = duration.as_nanos() as i128;
+    let time = if future { now + offset } else { now - offset };
+    // years 0000 to 9999
+    if !(-62_167_219_200_000_000_000..253_402_300_800_000_000_000).contains(&time) {
+        return Err(format!("{} is too far from now", arg));
+    }
+    Ok(from_unix_nanos(time))
+}
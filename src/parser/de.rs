@@ -0,0 +1,168 @@
+use serde::de::{self, DeserializeOwned, IntoDeserializer, MapAccess, Visitor};
+use serde::forward_to_deserialize_any;
+use super::convert::into_elements;
+use super::types::{CLPErrorKind, CLPResult, CLPVar};
+
+impl de::Error for CLPErrorKind {
+    fn custom<T: std::fmt::Display>(message: T) -> Self {
+        CLPErrorKind::TypeError(message.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        CLPErrorKind::MissingError(format!("no value for {}", field))
+    }
+}
+
+/// Prefixes the error of a value with the name it is stored under.
+fn with_name(name: &str, err: CLPErrorKind) -> CLPErrorKind {
+    match err {
+        CLPErrorKind::TypeError(message) => CLPErrorKind::TypeError(format!("{}: {}", name, message)),
+        CLPErrorKind::ConstraintError(message) => CLPErrorKind::ConstraintError(format!("{}: {}", name, message)),
+        err => err,
+    }
+}
+
+/// Deserializes `T` from the arguments of `result`, as in
+/// `let config: Config = from_result(&result)?`. Toggles that were not given
+/// have no value, unlike with `CLPResult::get` a `bool` field needs
+/// `#[serde(default)]` to be `false` rather than a `MissingError`.
+pub fn from_result<T: DeserializeOwned>(result: &CLPResult) -> Result<T, CLPErrorKind> {
+    T::deserialize(result)
+}
+
+/// Deserializes `T` from a single value.
+pub fn from_var<T: DeserializeOwned>(var: &CLPVar) -> Result<T, CLPErrorKind> {
+    T::deserialize(var.clone())
+}
+
+/// The arguments of a result as a map, errors carry the argument name.
+struct Arguments<I: Iterator<Item = (String, CLPVar)>> {
+    iter: I,
+    value: Option<(String, CLPVar)>
+}
+
+impl<'de, I: Iterator<Item = (String, CLPVar)>> MapAccess<'de> for Arguments<I> {
+    type Error = CLPErrorKind;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error> {
+        match self.iter.next() {
+            Some((name, var)) => {
+                let key = seed.deserialize(IntoDeserializer::<CLPErrorKind>::into_deserializer(name.as_str()))?;
+                self.value = Some((name, var));
+                Ok(Some(key))
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Self::Error> {
+        match self.value.take() {
+            Some((name, var)) => seed.deserialize(var).map_err(|err| with_name(&name, err)),
+            None => Err(CLPErrorKind::Error("value requested before its key".to_string())),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.iter.size_hint().1
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &CLPResult {
+    type Error = CLPErrorKind;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(Arguments {
+            iter: self.args.iter().map(|(name, var)| (name.clone(), var.clone())),
+            value: None
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl IntoDeserializer<'_, CLPErrorKind> for CLPVar {
+    type Deserializer = CLPVar;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+/// Vectors and tuples are sequences, maps are maps, durations are
+/// `[seconds, nanoseconds]` and addresses, dates and paths are strings.
+impl<'de> de::Deserializer<'de> for CLPVar {
+    type Error = CLPErrorKind;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let var = match into_elements(self) {
+            Ok(elements) => return visitor.visit_seq(de::value::SeqDeserializer::new(elements.into_iter())),
+            Err(var) => var,
+        };
+        match var {
+            CLPVar::Int(v) => match i64::try_from(v) {
+                Ok(v) => visitor.visit_i64(v),
+                Err(_) => visitor.visit_i128(v),
+            },
+            CLPVar::UInt(v) => match u64::try_from(v) {
+                Ok(v) => visitor.visit_u64(v),
+                Err(_) => visitor.visit_u128(v),
+            },
+            CLPVar::Float(v) => visitor.visit_f64(v),
+            CLPVar::String(v) => visitor.visit_string(v),
+            CLPVar::Duration(v) => {
+                let parts = [CLPVar::UInt(v.as_secs() as u128), CLPVar::UInt(v.subsec_nanos() as u128)];
+                visitor.visit_seq(de::value::SeqDeserializer::new(parts.into_iter()))
+            },
+            CLPVar::Size(v) => visitor.visit_u64(v),
+            CLPVar::IpAddr(v) => visitor.visit_string(v.to_string()),
+            CLPVar::SocketAddr(v) => visitor.visit_string(v.to_string()),
+            CLPVar::Map(v) => visitor.visit_map(Arguments { iter: v.into_iter(), value: None }),
+            CLPVar::Date(v) => visitor.visit_string(v.to_string()),
+            CLPVar::DateTime(v) => visitor.visit_string(v.to_string()),
+            CLPVar::Path(v) => match v.into_os_string().into_string() {
+                Ok(v) => visitor.visit_string(v),
+                Err(v) => Err(CLPErrorKind::TypeError(format!("{:?} is not valid UTF-8", v))),
+            },
+            CLPVar::OsString(v) => match v.into_string() {
+                Ok(v) => visitor.visit_string(v),
+                Err(v) => Err(CLPErrorKind::TypeError(format!("{:?} is not valid UTF-8", v))),
+            },
+            CLPVar::Flag(v) => visitor.visit_u64(v as u64),
+            var => Err(CLPErrorKind::TypeError(format!("{} can not be deserialized", var.type_name()))),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            CLPVar::Flag(count) => visitor.visit_bool(count > 0),
+            var => var.deserialize_any(visitor),
+        }
+    }
+
+    /// A value that is present is always `Some`, absent ones are `None`.
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Unit variants are given by name.
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            CLPVar::String(v) => visitor.visit_enum(v.into_deserializer()),
+            var => var.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
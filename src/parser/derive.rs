@@ -0,0 +1,70 @@
+use std::ffi::OsString;
+use std::time::SystemTime;
+use super::CommandLineParser;
+use super::types::{CLPError, CLPErrorKind, CLPResult};
+
+/// A struct whose fields are the arguments of a program, usually implemented
+/// with `#[derive(CLPArgs)]` from the `derive` feature.
+///
+/// Fields without `long` or `short` are required positionals in declaration
+/// order and come before any option, so they can not be an `Option` or have
+/// a default. `bool` fields are toggles and any other field is a keyword option that must
+/// be given unless it is an `Option` or has a default. The `#[arg(..)]`
+/// attribute takes
+///
+/// - `long`, `long = "name"`, `short`, `short = 'c'` and `alias = "--key"` for
+///   the keys of a keyword option, `long` defaults to the field name,
+/// - `name = ".."` for the name the value is stored under,
+/// - `ty = ..` for the `ArgTypes` when it is not inferred from the field type,
+/// - `constraint = ..` for a `CLPConstraint`, it may be repeated,
+/// - `default = ".."` parsed like an argument or `default = expr` of the
+///   field type,
+/// - `help = ".."`, `value_name = ".."` and `error = ".."` for the messages of
+///   a keyword option, doc comments are the help text otherwise,
+/// - `count` for an integer field counting a toggle and `rest` for a
+///   `Vec<String>` taking the arguments after the positionals.
+pub trait CLPArgs: Sized {
+    /// The parser for the arguments of `Self`.
+    fn parser() -> CommandLineParser;
+
+    /// Fills `Self` from the result of `parser()`. A `default = ".."` that
+    /// `defaults` did not store is parsed relative to the current time.
+    fn parse_from(result: &CLPResult) -> Result<Self, CLPErrorKind>;
+
+    /// Fills the arguments that were not given with their defaults, recorded
+    /// as `CLPSource::Default`, relative times are relative to `now`. Only
+    /// `default = ".."` is stored, a `default = expr` is not a `CLPVar` and
+    /// is applied by `parse_from`.
+    fn defaults(_result: &mut CLPResult, _now: SystemTime) -> Result<(), CLPErrorKind> {
+        Ok(())
+    }
+
+    /// The usage, positionals and keyword options with their help text.
+    fn help() -> String;
+
+    /// Parses `args`, which start with `arg0`, into `Self`.
+    fn parse<I>(args: I) -> Result<Self, CLPError>
+    where I: IntoIterator, I::Item: Into<OsString> {
+        parse_with(Self::parser(), args)
+    }
+
+    /// Like `parse` with relative times in arguments and defaults taken
+    /// relative to `now`.
+    fn parse_at<I>(args: I, now: SystemTime) -> Result<Self, CLPError>
+    where I: IntoIterator, I::Item: Into<OsString> {
+        parse_with(Self::parser().with_now(now), args)
+    }
+
+    /// Parses the arguments of the running process into `Self`.
+    fn parse_env() -> Result<Self, CLPError> {
+        parse_with(Self::parser(), std::env::args_os())
+    }
+}
+
+fn parse_with<T: CLPArgs, I>(parser: CommandLineParser, args: I) -> Result<T, CLPError>
+where I: IntoIterator, I::Item: Into<OsString> {
+    let now = parser.now.unwrap_or_else(SystemTime::now);
+    let mut result = parser.parse(args)?;
+    T::defaults(&mut result, now)?;
+    Ok(T::parse_from(&result)?)
+}
@@ -0,0 +1,49 @@
+use std::time::Duration;
+use super::numbers::scale;
+
+const UNITS: [(&str, u128); 7] = [
+    ("ns", 1),
+    ("us", 1_000),
+    ("ms", 1_000_000),
+    ("s", 1_000_000_000),
+    ("m", 60 * 1_000_000_000),
+    ("h", 60 * 60 * 1_000_000_000),
+    ("d", 24 * 60 * 60 * 1_000_000_000),
+];
+
+fn to_duration(nanos: u128) -> Result<Duration, String> {
+    let secs = u64::try_from(nanos / 1_000_000_000).map_err(|_| "duration is too large".to_string())?;
+    Ok(Duration::new(secs, (nanos % 1_000_000_000) as u32))
+}
+
+/// Parses durations like `30s`, `1h30m` or `1.5d`. A number without a unit is
+/// read in `bare_unit`, or rejected if that is `None`.
+pub(crate) fn parse_duration(arg: &str, bare_unit: Option<Duration>) -> Result<Duration, String> {
+    if arg.is_empty() {
+        return Err("expected a duration got an empty string".to_string());
+    }
+    if arg.chars().all(|char| char.is_ascii_digit() || char == '.') {
+        return match bare_unit {
+            Some(unit) => to_duration(scale(arg, unit.as_nanos())?),
+            None => Err(format!("missing unit after {}, expected one of ns, us, ms, s, m, h, d", arg)),
+        };
+    }
+    let mut rest = arg;
+    let mut nanos: u128 = 0;
+    while !rest.is_empty() {
+        let number_end = rest.find(|char: char| !(char.is_ascii_digit() || char == '.')).unwrap_or(rest.len());
+        let unit_end = rest[number_end..].find(|char: char| !char.is_ascii_alphabetic()).map_or(rest.len(), |end| number_end + end);
+        let (number, unit) = (&rest[..number_end], &rest[number_end..unit_end]);
+        if number.is_empty() {
+            return Err(format!("expected a number at {:?}", rest));
+        }
+        let unit = match UNITS.iter().find(|(name, _)| *name == unit) {
+            Some((_, unit)) => *unit,
+            None if unit.is_empty() => return Err(format!("missing unit after {}", number)),
+            None => return Err(format!("unknown unit {:?}, expected one of ns, us, ms, s, m, h, d", unit)),
+        };
+        nanos = nanos.checked_add(scale(number, unit)?).ok_or_else(|| "duration is too large".to_string())?;
+        rest = &rest[unit_end..];
+    }
+    to_duration(nanos)
+}
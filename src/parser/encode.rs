@@ -0,0 +1,192 @@
+use std::fmt::Write;
+use std::time::Duration;
+use super::convert::into_elements;
+use super::types::{CLPResult, CLPVar};
+
+/// Appends `value` as a JSON string.
+fn json_string(out: &mut String, value: &str) {
+    out.push('"');
+    for char in value.chars() {
+        match char {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            char if char < ' ' || char == '\u{7f}' => {
+                let _ = write!(out, "\\u{:04x}", char as u32);
+            },
+            char => out.push(char),
+        }
+    }
+    out.push('"');
+}
+
+/// Seconds with all nine decimals of the nanoseconds, trailing zeros trimmed.
+fn seconds(duration: &Duration) -> String {
+    match duration.subsec_nanos() {
+        0 => duration.as_secs().to_string(),
+        nanos => format!("{}.{}", duration.as_secs(), format!("{:09}", nanos).trim_end_matches('0')),
+    }
+}
+
+/// Values that are neither numbers nor sequences nor maps, as text.
+fn text(var: &CLPVar) -> String {
+    match var {
+        CLPVar::String(v) => v.clone(),
+        CLPVar::IpAddr(v) => v.to_string(),
+        CLPVar::SocketAddr(v) => v.to_string(),
+        CLPVar::Date(v) => v.to_string(),
+        CLPVar::DateTime(v) => v.to_string(),
+        CLPVar::Path(v) => v.to_string_lossy().into_owned(),
+        CLPVar::OsString(v) => v.to_string_lossy().into_owned(),
+        var => format!("{:?}", var),
+    }
+}
+
+fn json_var(out: &mut String, var: &CLPVar) {
+    match var {
+        CLPVar::Int(v) => {
+            let _ = write!(out, "{}", v);
+        },
+        CLPVar::UInt(v) => {
+            let _ = write!(out, "{}", v);
+        },
+        CLPVar::Size(v) => {
+            let _ = write!(out, "{}", v);
+        },
+        CLPVar::Flag(v) => {
+            let _ = write!(out, "{}", v);
+        },
+        CLPVar::Float(v) if v.is_nan() => out.push_str("\"NaN\""),
+        CLPVar::Float(v) if v.is_infinite() => out.push_str(if *v > 0.0 { "\"Infinity\"" } else { "\"-Infinity\"" }),
+        CLPVar::Float(v) => {
+            let _ = write!(out, "{:?}", v);
+        },
+        CLPVar::Duration(v) => out.push_str(&seconds(v)),
+        CLPVar::Map(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (index, key) in keys.into_iter().enumerate() {
+                if index > 0 {
+                    out.push_str(", ");
+                }
+                json_string(out, key);
+                out.push_str(": ");
+                json_var(out, &map[key]);
+            }
+            out.push('}');
+        },
+        var => match into_elements(var.clone()) {
+            Ok(elements) => {
+                out.push('[');
+                for (index, element) in elements.iter().enumerate() {
+                    if index > 0 {
+                        out.push_str(", ");
+                    }
+                    json_var(out, element);
+                }
+                out.push(']');
+            },
+            Err(var) => json_string(out, &text(&var)),
+        },
+    }
+}
+
+/// Single quotes `value` for sh, NUL bytes can not be held by a variable and
+/// are dropped.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\0', "").replace('\'', "'\\''"))
+}
+
+/// Turns `name` into a valid sh variable name in upper case.
+fn shell_name(name: &str) -> String {
+    let name: String = name.chars()
+        .map(|char| if char.is_ascii_alphanumeric() { char.to_ascii_uppercase() } else { '_' })
+        .collect();
+    match name.starts_with(|char: char| char.is_ascii_digit()) {
+        true => format!("_{}", name),
+        false => name,
+    }
+}
+
+fn shell_var(out: &mut String, name: &str, var: &CLPVar) {
+    let value = match var {
+        CLPVar::Int(v) => v.to_string(),
+        CLPVar::UInt(v) => v.to_string(),
+        CLPVar::Size(v) => v.to_string(),
+        CLPVar::Flag(v) => v.to_string(),
+        CLPVar::Float(v) => format!("{:?}", v),
+        CLPVar::Duration(v) => seconds(v),
+        CLPVar::Map(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let _ = writeln!(out, "{}_KEYS={}", name, shell_quote(&keys.iter().map(|key| key.as_str()).collect::<Vec<_>>().join(" ")));
+            for key in keys {
+                shell_var(out, &format!("{}_{}", name, shell_name(key)), &map[key]);
+            }
+            return;
+        },
+        var => match into_elements(var.clone()) {
+            Ok(elements) => {
+                let _ = writeln!(out, "{}_COUNT={}", name, elements.len());
+                for (index, element) in elements.iter().enumerate() {
+                    shell_var(out, &format!("{}_{}", name, index), element);
+                }
+                return;
+            },
+            Err(var) => text(&var),
+        },
+    };
+    let _ = writeln!(out, "{}={}", name, shell_quote(&value));
+}
+
+impl CLPVar {
+    /// The value as JSON. Durations are seconds, addresses, dates and paths
+    /// are strings and floats that are not finite are the strings `"NaN"`,
+    /// `"Infinity"` and `"-Infinity"`.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        json_var(&mut out, self);
+        out
+    }
+}
+
+impl CLPResult {
+    /// The result as a JSON object with `arg0`, `default`, `args` and
+    /// `sources`, the keys of `args` and `sources` are sorted.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"arg0\": ");
+        json_string(&mut out, &self.arg0);
+        out.push_str(", \"default\": ");
+        json_var(&mut out, &CLPVar::VecString(self.default.clone()));
+        out.push_str(", \"args\": ");
+        json_var(&mut out, &CLPVar::Map(self.args.clone()));
+        out.push_str(", \"sources\": ");
+        let sources = self.sources.iter().map(|(name, source)| (name.clone(), CLPVar::String(source.to_string())));
+        json_var(&mut out, &CLPVar::Map(sources.collect()));
+        out.push('}');
+        out
+    }
+
+    /// The result as `KEY='value'` lines that can be `eval`ed by sh. Names are
+    /// upper cased with characters other than letters and digits replaced by
+    /// `_` and `prefix` put in front, so that `PATH` or `HOME` are not
+    /// overwritten by accident. `arg0` becomes `{prefix}ARG0`, a vector sets
+    /// `NAME_COUNT` and `NAME_0`, `NAME_1`, .. and a map sets `NAME_KEYS` and
+    /// `NAME_KEY` for each key. `default` is the vector `{prefix}DEFAULT`.
+    pub fn to_shell(&self, prefix: &str) -> String {
+        let mut out = String::new();
+        shell_var(&mut out, &format!("{}ARG0", shell_name(prefix)), &CLPVar::String(self.arg0.clone()));
+        shell_var(&mut out, &format!("{}DEFAULT", shell_name(prefix)), &CLPVar::VecString(self.default.clone()));
+        let mut names: Vec<&String> = self.args.keys().collect();
+        names.sort();
+        for name in names {
+            shell_var(&mut out, &shell_name(&format!("{}{}", prefix, name)), &self.args[name]);
+        }
+        out
+    }
+}
@@ -1,287 +1,683 @@
 pub mod types;
+mod constraints;
+mod numbers;
+mod duration;
+mod size;
+mod net;
+mod split;
+mod ranges;
+mod strings;
+mod dates;
+mod result;
+mod encode;
+mod suggest;
+pub mod convert;
+pub mod derive;
+#[cfg(feature = "serde")]
+pub mod de;
 
-use std::{collections::HashMap};
-use types::{ArgsSettings, ArgTypes, KwargSettings, CLPResult, CLPVar, CLPErrorKind};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::SystemTime;
+use types::{ArgsSettings, ArgTypes, KwargSettings, KwargTypes, CLPInput, CLPResult, CLPVar, CLPError, CLPErrors, CLPErrorKind, CLPErrorReason, CLPDuplicate, CLPOccurrence, CLPSource, CLPTupleStyle, CLPVecSyntax};
 
 pub struct CommandLineParser {
     pub allow_more: bool,
     pub args: ArgsSettings,
-    pub kwargs: KwargSettings
+    pub kwargs: KwargSettings,
+    /// The time `RELTIME` inputs are relative to, the current time if `None`.
+    pub now: Option<SystemTime>,
+    /// Unknown options suggest the keys at most this many edits away, 0
+    /// turns suggestions off.
+    pub suggest_distance: usize
 }
 
-fn match_arg(argument: &ArgTypes, var_name: &String, arg: &String, vars: &mut HashMap<String, CLPVar>) -> Option<CLPErrorKind> {
-    //todo: parsing for vectors
-    match &argument {
-        ArgTypes::INT => {
-            let value: Result<i128, _> = arg.parse();
-            if let Err(_) = value {
-                return Some(CLPErrorKind::ParseError(format!("unable to parse {:?} as integer", arg))); 
-            }
-            let value = value.unwrap();
-            vars.insert(
-                var_name.clone(),
-                CLPVar::Int(value)
-            );
+fn split_vec<'a>(argument: &ArgTypes, arg: &'a str) -> Result<&'a str, CLPErrorKind> {
+    match arg.strip_prefix('[').and_then(|arg| arg.strip_suffix(']')) {
+        Some(inner) => Ok(inner),
+        None => Err(CLPErrorKind::ArgsError(format!("Expected {:?} got {:?}", argument, arg))),
+    }
+}
+
+/// Splits a vector argument into its elements. A single trailing delimiter is
+/// ignored, any other empty element is an error.
+fn vec_elements<'a>(argument: &ArgTypes, syntax: &CLPVecSyntax, arg: &'a str) -> Result<Vec<&'a str>, CLPErrorKind> {
+    let mut elements: Vec<&str> = match syntax {
+        CLPVecSyntax::Bracketed => {
+            let inner = split_vec(argument, arg)?;
+            split::split_outside(inner, ',').into_iter().map(str::trim).collect()
         },
-        ArgTypes::UINT => {
-            let value: Result<u128, _> = arg.parse();
-            if let Err(_) = value {
-                return Some(CLPErrorKind::ParseError(format!("unable to parse {:?} as unsigned integer", arg))); 
-            }
-            let value = value.unwrap();
-            vars.insert(
-                var_name.clone(),
-                CLPVar::UInt(value)
-            );
+        CLPVecSyntax::Bare => split::split_outside(arg, ',').into_iter().map(str::trim).collect(),
+        CLPVecSyntax::Separator(separator) => arg.split(*separator).map(str::trim).collect(),
+        CLPVecSyntax::Whitespace => arg.split_whitespace().collect(),
+    };
+    if elements.last() == Some(&"") {
+        elements.pop();
+    }
+    if let Some(index) = elements.iter().position(|element| element.is_empty()) {
+        return Err(CLPErrorKind::ParseError(format!("empty element at index {} in arg {}", index, arg)));
+    }
+    Ok(elements)
+}
+
+fn parse_vec<T>(argument: &ArgTypes, syntax: &CLPVecSyntax, arg: &str, name: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Vec<T>, CLPErrorKind> {
+    vec_elements(argument, syntax, arg)?.into_iter()
+        .map(|value| parse(value).ok_or_else(|| CLPErrorKind::ParseError(format!("Expected {} got {:?} in arg {}", name, value, arg))))
+        .collect()
+}
+
+fn parse_vec_vec<T>(argument: &ArgTypes, arg: &str, name: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Vec<Vec<T>>, CLPErrorKind> {
+    vec_elements(argument, &CLPVecSyntax::Bracketed, arg)?.into_iter()
+        .map(|row| parse_vec(argument, &CLPVecSyntax::Bracketed, row, name, &parse))
+        .collect()
+}
+
+fn parse_vec_string(argument: &ArgTypes, syntax: &CLPVecSyntax, arg: &str) -> Result<Vec<String>, CLPErrorKind> {
+    let elements = vec_elements(argument, syntax, arg)?;
+    match syntax {
+        CLPVecSyntax::Bracketed | CLPVecSyntax::Bare => elements.into_iter()
+            .enumerate()
+            .map(|(index, element)| strings::parse_item(element)
+                .map_err(|err| CLPErrorKind::ParseError(format!("{} at index {} in arg {}", err, index, arg))))
+            .collect(),
+        _ => Ok(elements.into_iter().map(str::to_string).collect()),
+    }
+}
+
+/// The original `VECSTRING` grammar: single quoted items with `/` as the
+/// escape character.
+fn parse_vec_string_legacy(argument: &ArgTypes, arg: &str) -> Result<Vec<String>, CLPErrorKind> {
+    let chars: Vec<char> = split_vec(argument, arg)?.chars().collect();
+    let mut v: Vec<String> = Vec::new();
+    let mut id: usize = 0;
+    let mut isfirst = true;
+    while id < chars.len() {
+        if chars[id] == ' ' {
+            id += 1;
+            continue;
         }
-        ArgTypes::FLOAT => {
-            let value: Result<f64, _> = arg.parse();
-            if let Err(_) = value {
-                return Some(CLPErrorKind::ParseError(format!("unable to parse {:?} as float", arg))); 
-            }
-            let value = value.unwrap();
-            vars.insert(
-                var_name.clone(),
-                CLPVar::Float(value)
-            );
-        },
-        ArgTypes::STRING => {
-            vars.insert(
-                var_name.clone(),
-                CLPVar::String(arg.clone())
-            );
-        },
-        ArgTypes::VECINT => {
-            let num_chars = arg.chars().count();
-            if num_chars < 2 {
-                return Some(CLPErrorKind::ArgsError(format!("Expected {:?} got {:?}", argument, arg)));
-            }
-            if arg.chars().nth(0).unwrap() == '[' && arg.chars().nth(num_chars - 1).unwrap() == ']' {
-                let mut v: Vec<i128> = Vec::new();
-                let ints: String = arg[1..num_chars-1].split(" ").collect(); 
-                for i in ints.split(",") {
-                    let value: Result<i128, _> = i.parse();
-                    if let Err(_) = value {
-                        return Some(CLPErrorKind::ParseError(format!("Expected INT got {:?} in arg {}", i, arg)));
-                    }
-                    v.push(value.unwrap());
-                }
-                vars.insert(
-                    var_name.clone(),
-                    CLPVar::VecInt(v)
-                );
-            }
-            else {
-                return Some(CLPErrorKind::ArgsError(format!("Expected {:?} got {:?}", argument, *arg)));
-            }
-        },
-        ArgTypes::VECUINT => {
-            let num_chars = arg.chars().count();
-            if num_chars < 2 {
-                return Some(CLPErrorKind::ArgsError(format!("Expected {:?} got {:?}", argument, arg)));
-            }
-            if arg.chars().nth(0).unwrap() == '[' && arg.chars().nth(num_chars - 1).unwrap() == ']' {
-                let mut v: Vec<u128> = Vec::new();
-                let uints: String = arg[1..num_chars-1].split(" ").collect(); 
-                for i in uints.split(",") {
-                    let value: Result<u128, _> = i.parse();
-                    if let Err(_) = value {
-                        return Some(CLPErrorKind::ParseError(format!("Expected UINT got {:?} in arg {}", i, arg)));
-                    }
-                    v.push(value.unwrap());
-                }
-                vars.insert(
-                    var_name.clone(),
-                    CLPVar::VecUInt(v)
-                );
-            }
-            else {
-                return Some(CLPErrorKind::ArgsError(format!("Expected {:?} got {:?}", argument, *arg)));
-            }
+        if !isfirst && chars[id] != ',' {
+            return Err(CLPErrorKind::ParseError(format!("expected , got {} at the end of the argument {}", chars[id], arg)));
+        } else if !isfirst {
+            isfirst = true;
+            id += 1;
+            continue;
         }
-        ArgTypes::VECFLOAT => {
-            let num_chars = arg.chars().count();
-            if num_chars < 2 {
-                return Some(CLPErrorKind::ArgsError(format!("Expected {:?} got {:?}", argument, arg)));
-            }
-            if arg.chars().nth(0).unwrap() == '[' && arg.chars().nth(num_chars - 1).unwrap() == ']' {
-                let mut v: Vec<f64> = Vec::new();
-                let floats: String = arg[1..num_chars-1].split(" ").collect(); 
-                for i in floats.split(",") {
-                    let value: Result<f64, _> = i.parse();
-                    if let Err(_) = value {
-                        return Some(CLPErrorKind::ParseError(format!("Expected FLOAT got {:?} in arg {}", i, arg)));
-                    }
-                    v.push(value.unwrap());
-                }
-                vars.insert(
-                    var_name.clone(),
-                    CLPVar::VecFloat(v)
-                );
-            }
-            else {
-                return Some(CLPErrorKind::ArgsError(format!("Expected {:?} got {:?}", argument, *arg)));
-            }
+        if chars[id] != '\'' {
+            return Err(CLPErrorKind::ParseError(format!("expected ' got {:?} at the end of the argument {}", chars[id], arg)));
         }
-        ArgTypes::VECSTRING => {
-            let num_chars = arg.chars().count();
-            if num_chars < 2 {
-                return Some(CLPErrorKind::ArgsError(format!("Expected {:?} got {:?}", argument, arg)));
-            }
-            if arg.chars().nth(0).unwrap() == '[' && arg.chars().nth(num_chars - 1).unwrap() == ']' {
-                let mut v: Vec<String> = Vec::new();
-                let mut string = String::new();
-                let mut id: usize = 1;
-                let mut isfirst = true;
-                while id < num_chars - 1 {
-                    if arg.chars().nth(id).unwrap() == ' ' {
-                        id += 1;
-                        continue;
-                    }
-                    if !isfirst && arg.chars().nth(id).unwrap() != ',' {
-                        return Some(CLPErrorKind::ParseError(format!("expected , got {} at the end of the argument {}", arg.chars().nth(id).unwrap(), arg)));
-                    } else if !isfirst {
-                        isfirst = true;
-                        id += 1;
-                        continue;
-                    }
-                    if arg.chars().nth(id).unwrap() == '\'' && isfirst {
-                        id += 1;
-                        while id < num_chars - 1 && arg.chars().nth(id).unwrap() != '\'' {
-                            if arg.chars().nth(id).unwrap() == '/' {
-                                let next_char = arg.chars().nth(id+1);
-                                if let Some(char) = next_char {
-                                    string.push(char);
-                                } else {
-                                    return Some(CLPErrorKind::ParseError(format!("unexpected / at the end of the argument {}", arg)));
-                                }
-                                id += 1;
-                            } else {
-                                string.push(arg.chars().nth(id).unwrap());
-                            }
-                            id += 1;
-                        }
-                        if arg.chars().nth(id).unwrap() != '\'' {
-                            return Some(CLPErrorKind::ParseError(format!("expected ' got End Of Argument {}", arg)));
-                        }
-                        v.push(string.clone());
-                        string = String::new();
-                        isfirst = false;
-                    } else if isfirst {
-                        return Some(CLPErrorKind::ParseError(format!("expected ' got {:?} at the end of the argument {}", arg.chars().nth(id).unwrap(), arg)));
-                    }
-                    id += 1;
-                }
-                if string != "" {
-                    v.push(string.clone());
+        let mut string = String::new();
+        id += 1;
+        while id < chars.len() && chars[id] != '\'' {
+            if chars[id] == '/' {
+                match chars.get(id + 1) {
+                    Some(char) => string.push(*char),
+                    None => return Err(CLPErrorKind::ParseError(format!("unexpected / at the end of the argument {}", arg))),
                 }
-                vars.insert(
-                    var_name.clone(),
-                    CLPVar::VecString(v)
-                );
-            }
-            else {
-                return Some(CLPErrorKind::ArgsError(format!("Expected {:?} got {:?}", argument, *arg)));
+                id += 1;
+            } else {
+                string.push(chars[id]);
             }
+            id += 1;
+        }
+        if id >= chars.len() {
+            return Err(CLPErrorKind::ParseError(format!("expected ' got End Of Argument {}", arg)));
         }
+        v.push(string);
+        isfirst = false;
+        id += 1;
+    }
+    Ok(v)
+}
+
+fn parse_map(value_type: &ArgTypes, input: &CLPInput, arg: &str, now: SystemTime) -> Result<HashMap<String, CLPVar>, CLPErrorKind> {
+    // a single trailing comma is ignored as in vectors
+    let pairs = match arg.starts_with('[') && arg.ends_with(']') {
+        true => vec_elements(&input.object_type, &CLPVecSyntax::Bracketed, arg)?,
+        false => vec![arg],
+    };
+    let unquote = |item: &str| strings::parse_item(item).map_err(|err| CLPErrorKind::ParseError(format!("{} in arg {}", err, arg)));
+    let mut map = HashMap::new();
+    for pair in pairs {
+        let key = split::split_outside(pair, '=')[0];
+        let (key, value) = match pair.get(key.len() + 1..) {
+            Some(value) if !key.trim().is_empty() => (unquote(key.trim())?, value.trim()),
+            _ => return Err(CLPErrorKind::ParseError(format!("Expected KEY=VALUE got {:?} in arg {}", pair.trim(), arg))),
+        };
+        let value = match value_type {
+            ArgTypes::STRING => CLPVar::String(unquote(value)?),
+            value_type => match_arg(value_type, input, value, now)?,
+        };
+        insert_pair(&mut map, key, value, &input.duplicate_keys)?;
+    }
+    Ok(map)
+}
+
+fn insert_pair(map: &mut HashMap<String, CLPVar>, key: String, value: CLPVar, duplicate_keys: &CLPDuplicate) -> Result<(), CLPErrorKind> {
+    match duplicate_keys {
+        CLPDuplicate::LastWins => {
+            map.insert(key, value);
+        },
+        CLPDuplicate::FirstWins => {
+            map.entry(key).or_insert(value);
+        },
+        CLPDuplicate::Error if map.contains_key(&key) => {
+            return Err(CLPErrorKind::ConstraintError(format!("duplicate key {:?}", key)));
+        },
+        CLPDuplicate::Error => {
+            map.insert(key, value);
+        },
+    }
+    Ok(())
+}
+
+fn parse_tuple(argument: &ArgTypes, types: &[ArgTypes], style: &CLPTupleStyle, input: &CLPInput, arg: &str, now: SystemTime) -> Result<Vec<CLPVar>, CLPErrorKind> {
+    let elements = match style {
+        CLPTupleStyle::Bracketed => split::split_outside(split_vec(argument, arg)?, ','),
+        CLPTupleStyle::Separated(separator) => split::split_outside(arg, *separator),
+    };
+    if elements.len() != types.len() {
+        return Err(CLPErrorKind::ArgsError(format!("Expected {:?} with {} elements got {} in arg {}", argument, types.len(), elements.len(), arg)));
     }
-    return None;
+    types.iter().zip(elements).enumerate()
+        .map(|(index, (element_type, element))| {
+            let element = element.trim();
+            let element = match element_type {
+                ArgTypes::STRING => strings::parse_item(element)
+                    .map(CLPVar::String)
+                    .map_err(|err| CLPErrorKind::ParseError(format!("{} in arg {}", err, arg))),
+                _ => match_arg(element_type, input, element, now),
+            };
+            element.map_err(|err| CLPErrorKind::ParseError(format!("element {} of {}: {}", index, arg, err.message())))
+        })
+        .collect()
+}
+
+fn match_arg(argument: &ArgTypes, input: &CLPInput, arg: &str, now: SystemTime) -> Result<CLPVar, CLPErrorKind> {
+    let parse_int = |value: &str| numbers::parse_int(value, input.decimal_only);
+    let parse_uint = |value: &str| numbers::parse_uint(value, input.decimal_only);
+    let parse_socket_addr = |value: &str| net::parse_socket_addr(value, input.default_port);
+    let syntax = &input.vec_syntax;
+    match argument {
+        ArgTypes::INT => parse_int(arg)
+            .map(CLPVar::Int)
+            .ok_or_else(|| CLPErrorKind::ParseError(format!("unable to parse {:?} as integer", arg))),
+        ArgTypes::UINT => parse_uint(arg)
+            .map(CLPVar::UInt)
+            .ok_or_else(|| CLPErrorKind::ParseError(format!("unable to parse {:?} as unsigned integer", arg))),
+        ArgTypes::FLOAT => arg.parse()
+            .map(CLPVar::Float)
+            .map_err(|_| CLPErrorKind::ParseError(format!("unable to parse {:?} as float", arg))),
+        ArgTypes::STRING => Ok(CLPVar::String(arg.to_string())),
+        ArgTypes::PATH => Ok(CLPVar::Path(arg.into())),
+        ArgTypes::OSSTRING => Ok(CLPVar::OsString(arg.into())),
+        ArgTypes::DURATION => duration::parse_duration(arg, input.duration_unit)
+            .map(CLPVar::Duration)
+            .map_err(|err| CLPErrorKind::ParseError(format!("unable to parse {:?} as duration, {}", arg, err))),
+        ArgTypes::SIZE => size::parse_size(arg)
+            .map(CLPVar::Size)
+            .map_err(|err| CLPErrorKind::ParseError(format!("unable to parse {:?} as size, {}", arg, err))),
+        ArgTypes::IPV4 => arg.parse::<Ipv4Addr>()
+            .map(|ip| CLPVar::IpAddr(IpAddr::V4(ip)))
+            .map_err(|_| CLPErrorKind::ParseError(format!("unable to parse {:?} as IPv4 address", arg))),
+        ArgTypes::IPV6 => arg.parse::<Ipv6Addr>()
+            .map(|ip| CLPVar::IpAddr(IpAddr::V6(ip)))
+            .map_err(|_| CLPErrorKind::ParseError(format!("unable to parse {:?} as IPv6 address", arg))),
+        ArgTypes::IPADDR => arg.parse()
+            .map(CLPVar::IpAddr)
+            .map_err(|_| CLPErrorKind::ParseError(format!("unable to parse {:?} as IP address", arg))),
+        ArgTypes::SOCKETADDR => parse_socket_addr(arg)
+            .map(CLPVar::SocketAddr)
+            .ok_or_else(|| CLPErrorKind::ParseError(format!("unable to parse {:?} as socket address", arg))),
+        ArgTypes::VECIPADDR => parse_vec(argument, syntax, arg, "IPADDR", |value| value.parse().ok()).map(CLPVar::VecIpAddr),
+        ArgTypes::VECSOCKETADDR => parse_vec(argument, syntax, arg, "SOCKETADDR", parse_socket_addr).map(CLPVar::VecSocketAddr),
+        ArgTypes::MAP(value_type) => parse_map(value_type, input, arg, now).map(CLPVar::Map),
+        ArgTypes::DATE => dates::parse_date(arg)
+            .map(CLPVar::Date)
+            .map_err(|err| CLPErrorKind::ParseError(format!("unable to parse {:?} as date, {}", arg, err))),
+        ArgTypes::DATETIME => dates::parse_datetime(arg)
+            .map(CLPVar::DateTime)
+            .map_err(|err| CLPErrorKind::ParseError(format!("unable to parse {:?} as date and time, {}", arg, err))),
+        ArgTypes::RELTIME => dates::parse_relative(arg, now)
+            .map(CLPVar::DateTime)
+            .map_err(|err| CLPErrorKind::ParseError(format!("unable to parse {:?} as time, {}", arg, err))),
+        ArgTypes::TUPLE(types, style) => parse_tuple(argument, types, style, input, arg, now).map(CLPVar::Tuple),
+        ArgTypes::VECINT => match &input.ranges {
+            Some(ranges) => ranges::expand(&vec_elements(argument, syntax, arg)?, ranges, arg, "INT", parse_int),
+            None => parse_vec(argument, syntax, arg, "INT", parse_int),
+        }.map(CLPVar::VecInt),
+        ArgTypes::VECUINT => match &input.ranges {
+            Some(ranges) => ranges::expand(&vec_elements(argument, syntax, arg)?, ranges, arg, "UINT", parse_uint),
+            None => parse_vec(argument, syntax, arg, "UINT", parse_uint),
+        }.map(CLPVar::VecUInt),
+        ArgTypes::VECFLOAT => parse_vec(argument, syntax, arg, "FLOAT", |value| value.parse().ok()).map(CLPVar::VecFloat),
+        ArgTypes::VECSTRING if input.legacy_escape => parse_vec_string_legacy(argument, arg).map(CLPVar::VecString),
+        ArgTypes::VECSTRING => parse_vec_string(argument, syntax, arg).map(CLPVar::VecString),
+        ArgTypes::VECVECINT => parse_vec_vec(argument, arg, "INT", parse_int).map(CLPVar::VecVecInt),
+        ArgTypes::VECVECUINT => parse_vec_vec(argument, arg, "UINT", parse_uint).map(CLPVar::VecVecUInt),
+        ArgTypes::VECVECFLOAT => parse_vec_vec(argument, arg, "FLOAT", |value| value.parse().ok()).map(CLPVar::VecVecFloat),
+    }
+}
+
+fn dedup_by_key<T, K: std::hash::Hash + Eq>(values: &mut Vec<T>, key: impl Fn(&T) -> K) {
+    let mut seen = std::collections::HashSet::new();
+    values.retain(|value| seen.insert(key(value)));
+}
+
+fn float_key(value: &f64) -> u64 {
+    // 0.0 and -0.0 compare equal
+    if *value == 0.0 { 0 } else { value.to_bits() }
+}
+
+/// Removes repeated elements of a vector value, keeping the first one.
+fn dedup(var: &mut CLPVar) {
+    match var {
+        CLPVar::VecInt(v) => dedup_by_key(v, |value| *value),
+        CLPVar::VecUInt(v) => dedup_by_key(v, |value| *value),
+        CLPVar::VecFloat(v) => dedup_by_key(v, float_key),
+        CLPVar::VecString(v) => dedup_by_key(v, |value| value.clone()),
+        CLPVar::VecVecInt(v) => dedup_by_key(v, |value| value.clone()),
+        CLPVar::VecVecUInt(v) => dedup_by_key(v, |value| value.clone()),
+        CLPVar::VecVecFloat(v) => dedup_by_key(v, |value| value.iter().map(float_key).collect::<Vec<_>>()),
+        CLPVar::VecIpAddr(v) => dedup_by_key(v, |value| *value),
+        CLPVar::VecSocketAddr(v) => dedup_by_key(v, |value| *value),
+        _ => (),
+    }
+}
+
+fn utf8<'a>(argument: &ArgTypes, arg: &'a OsStr) -> Result<&'a str, CLPErrorKind> {
+    arg.to_str().ok_or_else(|| CLPErrorKind::ParseError(format!("{:?} is not valid UTF-8, expected {:?}", arg, argument)))
+}
+
+/// An error parsing or checking a value.
+fn value_error(error: CLPErrorKind) -> CLPError {
+    match error {
+        CLPErrorKind::ConstraintError(_) => CLPError::new(CLPErrorReason::ConstraintViolation, error),
+        error => CLPError::new(CLPErrorReason::InvalidValue, error),
+    }
+}
+
+/// Whether `arg` looks like an option, it starts with `-` and is not a
+/// negative number, `-inf` and `-nan` included. Where a positional is
+/// expected such an argument is an unknown option whatever the positional's
+/// type.
+fn is_option(arg: &str) -> bool {
+    arg.len() > 1 && arg.starts_with('-')
+        && !arg[1..].starts_with(|char: char| char.is_ascii_digit() || char == '.')
+        && arg.parse::<f64>().is_err()
+}
+
+fn missing_arg0() -> CLPError {
+    CLPError::new(CLPErrorReason::Other, CLPErrorKind::Error("Expected the program name as the first argument".to_string()))
 }
 
+/// Where the errors of the main loop go. `FailFast` ends parsing with the
+/// first one, `Collect` keeps them and can not end parsing.
+trait Errors {
+    type Stop;
 
+    fn report(&mut self, error: CLPError) -> Result<(), Self::Stop>;
+
+    /// The errors reported so far.
+    fn reported(&self) -> &[CLPError];
+}
+
+struct FailFast;
+
+impl Errors for FailFast {
+    type Stop = CLPError;
+
+    fn report(&mut self, error: CLPError) -> Result<(), CLPError> {
+        Err(error)
+    }
+
+    fn reported(&self) -> &[CLPError] {
+        &[]
+    }
+}
+
+struct Collect(Vec<CLPError>);
+
+impl Errors for Collect {
+    type Stop = std::convert::Infallible;
+
+    fn report(&mut self, error: CLPError) -> Result<(), Self::Stop> {
+        self.0.push(error);
+        Ok(())
+    }
+
+    fn reported(&self) -> &[CLPError] {
+        &self.0
+    }
+}
+
+/// Parses `arg` into `vars` and returns the value of this occurrence.
+fn match_input(input: &CLPInput, var_name: &str, arg: &OsStr, now: SystemTime, vars: &mut HashMap<String, CLPVar>) -> Result<CLPVar, CLPErrorKind> {
+    // paths and raw strings keep arguments that are not valid UTF-8
+    let mut value = match &input.object_type {
+        ArgTypes::PATH => CLPVar::Path(arg.into()),
+        ArgTypes::OSSTRING => CLPVar::OsString(arg.into()),
+        argument => match_arg(argument, input, utf8(argument, arg)?, now)?,
+    };
+    if input.set {
+        dedup(&mut value);
+    }
+    let occurrence = value.clone();
+    // repeated map options collect into one map, constraints see all pairs
+    let value = match (vars.get(var_name), value) {
+        (Some(CLPVar::Map(map)), CLPVar::Map(pairs)) => {
+            let mut map = map.clone();
+            for (key, value) in pairs {
+                insert_pair(&mut map, key, value, &input.duplicate_keys)?;
+            }
+            CLPVar::Map(map)
+        },
+        (_, value) => value,
+    };
+    for constraint in &input.constraints {
+        if let Some(err) = constraints::check(constraint, var_name, &value) {
+            return Err(err);
+        }
+    }
+    vars.insert(var_name.to_string(), value);
+    Ok(occurrence)
+}
+
+impl CLPInput {
+    /// Parses `value` the way an argument for this input is parsed and checks
+    /// its constraints, as for a default value. Relative times are relative
+    /// to `now`.
+    pub fn parse_value(&self, value: &str, now: SystemTime) -> Result<CLPVar, CLPErrorKind> {
+        match_input(self, &self.name, OsStr::new(value), now, &mut HashMap::new())
+    }
+}
 
 impl CommandLineParser {
     pub fn new(allow_more: bool, args: ArgsSettings, kwargs:KwargSettings) -> CommandLineParser {
         CommandLineParser {
             allow_more,
             args,
-            kwargs
+            kwargs,
+            now: None,
+            suggest_distance: 2
+        }
+    }
+
+    pub fn with_now(mut self, now: SystemTime) -> CommandLineParser {
+        self.now = Some(now);
+        self
+    }
+
+    pub fn with_suggest_distance(mut self, distance: usize) -> CommandLineParser {
+        self.suggest_distance = distance;
+        self
+    }
+
+    /// The keys closest to the unknown option `arg`.
+    fn suggest(&self, arg: &OsStr) -> Vec<&str> {
+        match arg.to_str() {
+            Some(arg) if self.suggest_distance > 0 && is_option(arg) => {
+                let word = arg.split_once('=').map_or(arg, |(key, _)| key);
+                suggest::closest(word, self.kwargs.keyvalues.keys().map(String::as_str), self.suggest_distance)
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    /// An argument nothing takes, told apart into an unknown option with the
+    /// keys it may be a typo of and an unexpected positional.
+    fn unexpected(&self, arg: &OsStr, position: usize) -> CLPError {
+        let error = match arg.to_str().is_some_and(is_option) {
+            true => CLPError::new(CLPErrorReason::UnknownOption, CLPErrorKind::Error(format!("Unknown option {}{}", arg.to_string_lossy(), suggest::did_you_mean(&self.suggest(arg))))),
+            false => CLPError::new(CLPErrorReason::UnexpectedArgument, CLPErrorKind::Error(format!("Unexpected input {}", arg.to_string_lossy()))),
+        };
+        error.token(arg).index(position)
+    }
+
+    /// Looks up the keyword option `arg` refers to. Short options that take a
+    /// value may have it attached, as in `-O3` or `-Dkey=value`.
+    fn find_kwarg<'b>(&self, arg: &'b str) -> Option<(&str, Option<&'b str>)> {
+        if let Some((key, _)) = self.kwargs.keyvalues.get_key_value(arg) {
+            return Some((key, None));
         }
+        self.kwargs.keyvalues.iter()
+            .filter(|(key, kwarg)| key.len() == 2 && key.starts_with('-') && !matches!(kwarg, KwargTypes::Toggle(_)))
+            .find(|(key, _)| arg.len() > 2 && arg.starts_with(key.as_str()))
+            .map(|(key, _)| (key.as_str(), Some(&arg[2..])))
     }
-    pub fn parse(&self, args: &Vec<String>) -> Result<CLPResult, CLPErrorKind> {
-            
-        let mut iter = args.iter();
-            
+
+    /// Parses the value of the keyword option `key` given at `position` and
+    /// returns the name it is stored under with the value of this occurrence.
+    fn match_kwarg<'a, 's>(&'s self, key: &'s str, attached: Option<&str>, position: usize, iter: &mut impl Iterator<Item = &'a OsStr>, now: SystemTime, vars: &mut HashMap<String, CLPVar>) -> Result<(&'s str, CLPVar), CLPError> {
+        match &self.kwargs.keyvalues[key] {
+            KwargTypes::Important(input, messages) | KwargTypes::Optional(input, messages) => {
+                let (value, value_position) = match attached.map(OsStr::new) {
+                    Some(value) => (value, position),
+                    None => match iter.next() {
+                        Some(value) => (value, position + 1),
+                        None => {
+                            let error = CLPErrorKind::KwargError(format!("Expected {} {} got End Of Input", key, messages[1]));
+                            return Err(CLPError::new(CLPErrorReason::MissingValue, error).arg(key).index(position).expected(&input.object_type));
+                        },
+                    },
+                };
+                let error = match match_input(input, &input.name, value, now, vars) {
+                    Ok(var) => return Ok((input.name.as_str(), var)),
+                    Err(CLPErrorKind::ParseError(_)) | Err(CLPErrorKind::ArgsError(_)) if !messages[2].is_empty() => {
+                        CLPError::new(CLPErrorReason::InvalidValue, CLPErrorKind::KwargError(messages[2].replacen("{}", &value.to_string_lossy(), 1)))
+                    },
+                    Err(error) => value_error(error),
+                };
+                Err(error.arg(key).token(value).index(value_position).expected(&input.object_type))
+            },
+            KwargTypes::Toggle(_) => {
+                let count = match vars.get(key.trim_start_matches('-')) {
+                    Some(CLPVar::Flag(count)) => *count,
+                    _ => 0,
+                };
+                vars.insert(key.trim_start_matches('-').to_string(), CLPVar::Flag(count + 1));
+                Ok((key.trim_start_matches('-'), CLPVar::Flag(1)))
+            },
+        }
+    }
+
+    /// Parses the arguments of the program, `args` starts with `arg0`. Any
+    /// iterator of `String`, `&str`, `OsString` or `&OsStr` items works, as
+    /// `std::env::args_os()`. Arguments that are not valid UTF-8 are only
+    /// accepted by `PATH` and `OSSTRING` inputs, anywhere else they are an
+    /// error.
+    pub fn parse<I>(&self, args: I) -> Result<CLPResult, CLPError>
+    where I: IntoIterator, I::Item: Into<OsString> {
+        let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+        let mut iter = args.iter().map(OsString::as_os_str);
+        let arg0 = match iter.next() {
+            Some(arg0) => arg0.to_string_lossy().into_owned(),
+            None => return Err(missing_arg0()),
+        };
+        self.parse_os_iter(arg0, 1, iter, &mut FailFast)
+    }
+
+    /// Like `parse` but keeps going after bad values, unknown options and
+    /// missing inputs. All of them are reported at once in argv order, with
+    /// the values that did parse attached.
+    pub fn parse_all<I>(&self, args: I) -> Result<CLPResult, CLPErrors>
+    where I: IntoIterator, I::Item: Into<OsString> {
+        let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+        let mut iter = args.iter().map(OsString::as_os_str);
+        let arg0 = match iter.next() {
+            Some(arg0) => arg0.to_string_lossy().into_owned(),
+            None => return Err(CLPErrors { errors: vec![missing_arg0()], result: Box::default() }),
+        };
+        let mut errors = Collect(Vec::new());
+        let result = match self.parse_os_iter(arg0, 1, iter, &mut errors) {
+            Ok(result) => result,
+            Err(never) => match never {},
+        };
+        match errors.0.is_empty() {
+            true => Ok(result),
+            false => Err(CLPErrors { errors: errors.0, result: Box::new(result) }),
+        }
+    }
+
+    /// Like `parse` for arguments without `arg0`, as handed over by another
+    /// parser. `arg0` of the result is empty.
+    pub fn parse_without_arg0<I>(&self, args: I) -> Result<CLPResult, CLPError>
+    where I: IntoIterator, I::Item: Into<OsString> {
+        let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+        self.parse_os_iter(String::new(), 0, args.iter().map(OsString::as_os_str), &mut FailFast)
+    }
+
+    /// Parses the arguments of the running process.
+    pub fn parse_env(&self) -> Result<CLPResult, CLPError> {
+        self.parse(std::env::args_os())
+    }
+
+    /// The main loop, `first` is the argv index of the first item of `iter`.
+    /// Errors go through `errors`, which decides whether parsing goes on.
+    fn parse_os_iter<'a, E>(&self, arg0: String, first: usize, iter: impl Iterator<Item = &'a OsStr>, errors: &mut impl Errors<Stop = E>) -> Result<CLPResult, E> {
+
         let mut vars: HashMap<String, CLPVar> = HashMap::new();
+        let mut sources: HashMap<String, CLPSource> = HashMap::new();
+        let mut occurrences: Vec<CLPOccurrence> = Vec::new();
         let mut default: Vec<String> = Vec::new();
-        let arg0 = iter.next().unwrap().clone(); 
         let get_default = self.allow_more;
+        let now = self.now.unwrap_or_else(SystemTime::now);
+        let mut iter = iter.enumerate().map(|(index, arg)| (first + index, arg));
 
         match &self.args {
             ArgsSettings::Args(arguments) => {
                 let mut index: usize = 0;
-                while let Some(arg) = iter.next() {
-                    let toggle = self.kwargs.keyvalues.contains_key(arg);
-                    if toggle {
+                while let Some((position, arg)) = iter.next() {
+                    if let Some((key, attached)) = arg.to_str().and_then(|arg| self.find_kwarg(arg)) {
                         if index < arguments.len() {
-                            return Err(CLPErrorKind::ArgsError(format!("Expected {:?} got {}", arguments[index], arg)));
-                        } else {
-                            todo!()
+                            let error = CLPErrorKind::ArgsError(format!("Expected {:?} got {}", arguments[index], arg.to_string_lossy()));
+                            errors.report(CLPError::new(CLPErrorReason::MisplacedOption, error)
+                                .arg(&arguments[index].name)
+                                .token(arg)
+                                .index(position)
+                                .expected(&arguments[index].object_type))?;
                         }
-                    } else {
-                        if index < arguments.len() {
-                            let result = match_arg(&arguments[index].object_type, &arguments[index].name, arg, &mut vars);
-                            if let Some(err) = result {
-                                return Err(err);
-                            }
-                        } else if get_default {
-                            default.push(arg.clone());
-                        } else {
-                            return Err(CLPErrorKind::Error(format!("Unexpected input {}", arg)));
+                        match self.match_kwarg(key, attached, position, &mut (&mut iter).map(|(_, arg)| arg), now, &mut vars) {
+                            Ok((name, value)) => {
+                                sources.insert(name.to_string(), CLPSource::CommandLine(position));
+                                occurrences.push(CLPOccurrence { name: name.to_string(), key: Some(key.to_string()), index: position, value });
+                            },
+                            Err(error) => errors.report(error)?,
+                        }
+                        continue;
+                    }
+                    // an option that is not a key takes no positional, even a string
+                    if index < arguments.len() && arg.to_str().is_some_and(is_option) {
+                        errors.report(self.unexpected(arg, position))?;
+                        continue;
+                    }
+                    if index < arguments.len() {
+                        let input = &arguments[index];
+                        match match_input(input, &input.name, arg, now, &mut vars) {
+                            Ok(value) => {
+                                sources.insert(input.name.clone(), CLPSource::CommandLine(position));
+                                occurrences.push(CLPOccurrence { name: input.name.clone(), key: None, index: position, value });
+                            },
+                            Err(error) => errors.report(value_error(error).arg(&input.name).token(arg).index(position).expected(&input.object_type))?,
+                        }
+                    } else if get_default {
+                        match utf8(&ArgTypes::STRING, arg) {
+                            Ok(arg) => default.push(arg.to_string()),
+                            Err(error) => errors.report(value_error(error).token(arg).index(position))?,
                         }
+                    } else {
+                        errors.report(self.unexpected(arg, position))?;
                     }
                     index += 1;
                 }
-                if index < arguments.len() {
-                    return Err(CLPErrorKind::ArgsError(format!("Didn't get input for {:?}", arguments[index])));
+                for input in arguments.iter().skip(index) {
+                    let error = CLPErrorKind::ArgsError(format!("Didn't get input for {:?}", input));
+                    errors.report(CLPError::new(CLPErrorReason::MissingArgument, error).arg(&input.name).expected(&input.object_type))?;
                 }
             }
             ArgsSettings::ALL(expected) => {
                 let mut index: usize = 0;
                 let mut inlist = true;
-                for arg in iter {
-                    let toggle = self.kwargs.keyvalues.contains_key(arg);
-                    if toggle {
+                while let Some((position, arg)) = iter.next() {
+                    if let Some((key, attached)) = arg.to_str().and_then(|arg| self.find_kwarg(arg)) {
                         inlist = false;
-                    }
-                    if inlist {
+                        match self.match_kwarg(key, attached, position, &mut (&mut iter).map(|(_, arg)| arg), now, &mut vars) {
+                            Ok((name, value)) => {
+                                sources.insert(name.to_string(), CLPSource::CommandLine(position));
+                                occurrences.push(CLPOccurrence { name: name.to_string(), key: Some(key.to_string()), index: position, value });
+                            },
+                            Err(error) => errors.report(error)?,
+                        }
+                    } else if inlist && arg.to_str().is_some_and(is_option) {
+                        errors.report(self.unexpected(arg, position))?;
+                    } else if inlist {
                         let name = expected.name.clone() + format!("{}", index).as_str();
-                        let result = match_arg(&expected.object_type, &name, arg, &mut vars);
-                        if let Some(err) = result {
-                            return Err(err);
+                        match match_input(expected, &name, arg, now, &mut vars) {
+                            Ok(value) => {
+                                sources.insert(name.clone(), CLPSource::CommandLine(position));
+                                occurrences.push(CLPOccurrence { name, key: None, index: position, value });
+                            },
+                            Err(error) => errors.report(value_error(error).arg(&name).token(arg).index(position).expected(&expected.object_type))?,
                         }
-                    } else {
-                        if !toggle && get_default {
-                            default.push(arg.clone());
-                        } else if !toggle {
-                            return Err(CLPErrorKind::Error(format!("Unexpected input {}", arg)));
-                        } else {
-                            todo!();
+                        index += 1;
+                    } else if get_default {
+                        match utf8(&ArgTypes::STRING, arg) {
+                            Ok(arg) => default.push(arg.to_string()),
+                            Err(error) => errors.report(value_error(error).token(arg).index(position))?,
                         }
+                    } else {
+                        errors.report(self.unexpected(arg, position))?;
                     }
-                    index += 1;
                 }
             },
             ArgsSettings::NONE => {
-                while let Some(arg) = iter.next() {
-                    let toggle = self.kwargs.keyvalues.contains_key(arg);
-                    if toggle {
-                        todo!();
-                    } else {
-                        if get_default {
-                            default.push(arg.clone());
-                        } else {
-                            return Err(CLPErrorKind::Error(format!("Unexpected input {}", arg)));
+                while let Some((position, arg)) = iter.next() {
+                    if let Some((key, attached)) = arg.to_str().and_then(|arg| self.find_kwarg(arg)) {
+                        match self.match_kwarg(key, attached, position, &mut (&mut iter).map(|(_, arg)| arg), now, &mut vars) {
+                            Ok((name, value)) => {
+                                sources.insert(name.to_string(), CLPSource::CommandLine(position));
+                                occurrences.push(CLPOccurrence { name: name.to_string(), key: Some(key.to_string()), index: position, value });
+                            },
+                            Err(error) => errors.report(error)?,
+                        }
+                    } else if get_default {
+                        match utf8(&ArgTypes::STRING, arg) {
+                            Ok(arg) => default.push(arg.to_string()),
+                            Err(error) => errors.report(value_error(error).token(arg).index(position))?,
                         }
+                    } else {
+                        errors.report(self.unexpected(arg, position))?;
                     }
                 }
             }
         }
 
-        return Ok(CLPResult {
+        // options with a bad value are already reported, aliases only once
+        let mut reported: Vec<&str> = errors.reported().iter()
+            .filter_map(|error| match error.arg.as_ref().and_then(|arg| self.kwargs.keyvalues.get(arg)) {
+                Some(KwargTypes::Important(input, _)) | Some(KwargTypes::Optional(input, _)) => Some(input.name.as_str()),
+                _ => None,
+            })
+            .collect();
+        let mut keys: Vec<&String> = self.kwargs.keyvalues.keys().collect();
+        keys.sort();
+        for key in keys {
+            if let KwargTypes::Important(input, _) = &self.kwargs.keyvalues[key] {
+                if !vars.contains_key(&input.name) && !reported.contains(&input.name.as_str()) {
+                    reported.push(&input.name);
+                    let error = CLPErrorKind::KwargError(format!("Didn't get input for {}", key));
+                    errors.report(CLPError::new(CLPErrorReason::MissingOption, error).arg(key).expected(&input.object_type))?;
+                }
+            }
+        }
+
+        Ok(CLPResult {
             arg0,
             default,
-            args: vars
-        });
+            args: vars,
+            sources,
+            occurrences
+        })
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,13 @@
+use std::net::{IpAddr, SocketAddr};
+
+/// Parses `host:port` socket addresses. Without a port, `default_port` is used
+/// for a bare IP address or a bracketed IPv6 address like `[::1]`.
+pub(crate) fn parse_socket_addr(arg: &str, default_port: Option<u16>) -> Option<SocketAddr> {
+    if let Ok(addr) = arg.parse() {
+        return Some(addr);
+    }
+    let port = default_port?;
+    let host = arg.strip_prefix('[').and_then(|host| host.strip_suffix(']')).unwrap_or(arg);
+    let ip: IpAddr = host.parse().ok()?;
+    Some(SocketAddr::new(ip, port))
+}
@@ -0,0 +1,66 @@
+/// Splits off an optional sign and returns `(negative, rest)`.
+fn split_sign(arg: &str) -> (bool, &str) {
+    if let Some(rest) = arg.strip_prefix('-') {
+        (true, rest)
+    } else {
+        (false, arg.strip_prefix('+').unwrap_or(arg))
+    }
+}
+
+/// Parses the magnitude of an integer literal: `0x`, `0o` and `0b` radix
+/// prefixes and `_` between digits are accepted.
+fn parse_magnitude(arg: &str) -> Option<u128> {
+    let (radix, digits) = match arg.get(..2) {
+        Some("0x") | Some("0X") => (16, &arg[2..]),
+        Some("0o") | Some("0O") => (8, &arg[2..]),
+        Some("0b") | Some("0B") => (2, &arg[2..]),
+        _ => (10, arg),
+    };
+    if digits.starts_with('_') || digits.ends_with('_') || digits.starts_with(['+', '-']) {
+        return None;
+    }
+    let digits: String = digits.chars().filter(|char| *char != '_').collect();
+    u128::from_str_radix(&digits, radix).ok()
+}
+
+pub(crate) fn parse_uint(arg: &str, decimal_only: bool) -> Option<u128> {
+    if decimal_only {
+        return arg.parse().ok();
+    }
+    match split_sign(arg) {
+        (false, magnitude) => parse_magnitude(magnitude),
+        (true, _) => None,
+    }
+}
+
+pub(crate) fn parse_int(arg: &str, decimal_only: bool) -> Option<i128> {
+    if decimal_only {
+        return arg.parse().ok();
+    }
+    let (negative, magnitude) = split_sign(arg);
+    let magnitude = parse_magnitude(magnitude)?;
+    if negative {
+        0i128.checked_sub_unsigned(magnitude)
+    } else {
+        i128::try_from(magnitude).ok()
+    }
+}
+
+/// Multiplies a decimal number like `1.5` by `unit`, dropping any fraction
+/// left in the result.
+pub(crate) fn scale(number: &str, unit: u128) -> Result<u128, String> {
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return Err(format!("expected a number got {:?}", number));
+    }
+    let overflow = || format!("{} is too large", number);
+    let whole: u128 = if whole.is_empty() { 0 } else { whole.parse().map_err(|_| overflow())? };
+    let mut value = whole.checked_mul(unit).ok_or_else(overflow)?;
+    // digits beyond the 18th can not change the result by a whole unit
+    let fraction = &fraction[..fraction.len().min(18)];
+    if !fraction.is_empty() {
+        let numerator: u128 = fraction.parse().map_err(|_| format!("expected a number got {:?}", number))?;
+        value = value.checked_add(numerator * unit / 10u128.pow(fraction.len() as u32)).ok_or_else(overflow)?;
+    }
+    Ok(value)
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use super::types::{CLPErrorKind, CLPRanges};
+
+/// Range lists expanding to more elements than this are rejected.
+const MAX_RANGE_LEN: u128 = 1 << 20;
+
+pub(crate) trait RangeInt: Copy + Ord + std::hash::Hash + std::fmt::Display {
+    fn add_step(self, step: u128) -> Option<Self>;
+    fn sub_step(self, step: u128) -> Option<Self>;
+    fn distance(self, other: Self) -> u128;
+}
+
+impl RangeInt for i128 {
+    fn add_step(self, step: u128) -> Option<Self> {
+        self.checked_add_unsigned(step)
+    }
+    fn sub_step(self, step: u128) -> Option<Self> {
+        self.checked_sub_unsigned(step)
+    }
+    fn distance(self, other: Self) -> u128 {
+        self.abs_diff(other)
+    }
+}
+
+impl RangeInt for u128 {
+    fn add_step(self, step: u128) -> Option<Self> {
+        self.checked_add(step)
+    }
+    fn sub_step(self, step: u128) -> Option<Self> {
+        self.checked_sub(step)
+    }
+    fn distance(self, other: Self) -> u128 {
+        self.abs_diff(other)
+    }
+}
+
+/// Splits `a-b`, `a..b` and `a..=b` into their bounds, the end bound of `a..b`
+/// is exclusive.
+fn split_range(element: &str) -> Option<(&str, &str, bool)> {
+    if let Some((start, end)) = element.split_once("..=") {
+        return Some((start, end, true));
+    }
+    if let Some((start, end)) = element.split_once("..") {
+        return Some((start, end, false));
+    }
+    // a leading - is the sign of the start
+    element.char_indices()
+        .skip(1)
+        .find(|(_, char)| *char == '-')
+        .map(|(id, _)| (&element[..id], &element[id + 1..], true))
+}
+
+/// Expands range list elements like `1-5`, `10..12` and `0..100:10` into
+/// their values.
+pub(crate) fn expand<T: RangeInt>(elements: &[&str], ranges: &CLPRanges, arg: &str, name: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Vec<T>, CLPErrorKind> {
+    let parse_error = |value: &str| CLPErrorKind::ParseError(format!("Expected {} got {:?} in arg {}", name, value, arg));
+    let mut values = Vec::new();
+    // the element each value came from, to report overlaps
+    let mut seen: HashMap<T, &str> = HashMap::new();
+    for element in elements {
+        let (range, step) = match element.split_once(':') {
+            Some((range, step)) => match super::numbers::parse_uint(step, false) {
+                Some(step) if step > 0 => (range, step),
+                _ => return Err(CLPErrorKind::ParseError(format!("Expected a positive step got {:?} in arg {}", step, arg))),
+            },
+            None => (*element, 1),
+        };
+        let (start, end) = match split_range(range) {
+            Some((start, end, inclusive)) => {
+                let start = parse(start).ok_or_else(|| parse_error(start))?;
+                let end = parse(end).ok_or_else(|| parse_error(end))?;
+                if end < start && ranges.reject_reversed {
+                    return Err(CLPErrorKind::ConstraintError(format!("range {} is reversed in arg {}", element, arg)));
+                }
+                if !inclusive && start == end {
+                    continue;
+                }
+                let end = match (inclusive, start.cmp(&end)) {
+                    (true, _) | (false, std::cmp::Ordering::Equal) => end,
+                    (false, std::cmp::Ordering::Less) => end.sub_step(1).unwrap_or(end),
+                    (false, std::cmp::Ordering::Greater) => end.add_step(1).unwrap_or(end),
+                };
+                (start, end)
+            },
+            None => {
+                let value = parse(range).ok_or_else(|| parse_error(range))?;
+                (value, value)
+            },
+        };
+        if values.len() as u128 + start.distance(end) / step >= MAX_RANGE_LEN {
+            return Err(CLPErrorKind::ConstraintError(format!("arg {} expands to more than {} elements", arg, MAX_RANGE_LEN)));
+        }
+        let mut value = start;
+        loop {
+            if ranges.reject_overlap {
+                if let Some(other) = seen.insert(value, element) {
+                    return Err(CLPErrorKind::ConstraintError(format!("{} overlaps {} in arg {}", element, other, arg)));
+                }
+            }
+            values.push(value);
+            let next = if start <= end { value.add_step(step) } else { value.sub_step(step) };
+            match next {
+                Some(next) if (start <= end && next <= end) || (start > end && next >= end) => value = next,
+                _ => break,
+            }
+        }
+    }
+    if ranges.dedup_sort {
+        values.sort();
+        values.dedup();
+    }
+    Ok(values)
+}
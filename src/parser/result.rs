@@ -0,0 +1,220 @@
+use std::path::Path;
+use super::types::{CLPErrorKind, CLPOccurrence, CLPResult, CLPSource, CLPVar};
+
+impl CLPVar {
+    /// The name of the variant, used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            CLPVar::Int(_) => "Int",
+            CLPVar::UInt(_) => "UInt",
+            CLPVar::Float(_) => "Float",
+            CLPVar::String(_) => "String",
+            CLPVar::VecInt(_) => "VecInt",
+            CLPVar::VecUInt(_) => "VecUInt",
+            CLPVar::VecFloat(_) => "VecFloat",
+            CLPVar::VecString(_) => "VecString",
+            CLPVar::VecVecInt(_) => "VecVecInt",
+            CLPVar::VecVecUInt(_) => "VecVecUInt",
+            CLPVar::VecVecFloat(_) => "VecVecFloat",
+            CLPVar::Duration(_) => "Duration",
+            CLPVar::Size(_) => "Size",
+            CLPVar::IpAddr(_) => "IpAddr",
+            CLPVar::SocketAddr(_) => "SocketAddr",
+            CLPVar::VecIpAddr(_) => "VecIpAddr",
+            CLPVar::VecSocketAddr(_) => "VecSocketAddr",
+            CLPVar::Map(_) => "Map",
+            CLPVar::Tuple(_) => "Tuple",
+            CLPVar::Date(_) => "Date",
+            CLPVar::DateTime(_) => "DateTime",
+            CLPVar::Path(_) => "Path",
+            CLPVar::OsString(_) => "OsString",
+            CLPVar::Flag(_) => "Flag",
+        }
+    }
+}
+
+fn wrong_type(name: &str, expected: &str, found: &CLPVar) -> CLPErrorKind {
+    CLPErrorKind::TypeError(format!("{} is {}, expected {}", name, found.type_name(), expected))
+}
+
+/// Replaces a missing value by `default`, other errors are kept.
+fn or<T>(result: Result<T, CLPErrorKind>, default: T) -> Result<T, CLPErrorKind> {
+    match result {
+        Err(CLPErrorKind::MissingError(_)) => Ok(default),
+        result => result,
+    }
+}
+
+/// Typed access to the parsed values. Every getter reports a `MissingError`
+/// when `name` has no value and a `TypeError` when it holds another type, the
+/// `_or` variants return `default` instead of a `MissingError`.
+impl CLPResult {
+    pub fn get_var(&self, name: &str) -> Result<&CLPVar, CLPErrorKind> {
+        self.args.get(name).ok_or_else(|| CLPErrorKind::MissingError(format!("no value for {}", name)))
+    }
+
+    fn get_as<'a, T>(&'a self, name: &str, expected: &str, get: impl Fn(&'a CLPVar) -> Option<T>) -> Result<T, CLPErrorKind> {
+        let var = self.get_var(name)?;
+        get(var).ok_or_else(|| wrong_type(name, expected, var))
+    }
+
+    pub fn get_int(&self, name: &str) -> Result<i128, CLPErrorKind> {
+        self.get_as(name, "Int", |var| match var { CLPVar::Int(v) => Some(*v), _ => None })
+    }
+
+    pub fn get_int_or(&self, name: &str, default: i128) -> Result<i128, CLPErrorKind> {
+        or(self.get_int(name), default)
+    }
+
+    pub fn get_uint(&self, name: &str) -> Result<u128, CLPErrorKind> {
+        self.get_as(name, "UInt", |var| match var { CLPVar::UInt(v) => Some(*v), _ => None })
+    }
+
+    pub fn get_uint_or(&self, name: &str, default: u128) -> Result<u128, CLPErrorKind> {
+        or(self.get_uint(name), default)
+    }
+
+    pub fn get_float(&self, name: &str) -> Result<f64, CLPErrorKind> {
+        self.get_as(name, "Float", |var| match var { CLPVar::Float(v) => Some(*v), _ => None })
+    }
+
+    pub fn get_float_or(&self, name: &str, default: f64) -> Result<f64, CLPErrorKind> {
+        or(self.get_float(name), default)
+    }
+
+    pub fn get_str(&self, name: &str) -> Result<&str, CLPErrorKind> {
+        self.get_as(name, "String", |var| match var { CLPVar::String(v) => Some(v.as_str()), _ => None })
+    }
+
+    pub fn get_str_or<'a>(&'a self, name: &str, default: &'a str) -> Result<&'a str, CLPErrorKind> {
+        or(self.get_str(name), default)
+    }
+
+    pub fn get_path(&self, name: &str) -> Result<&Path, CLPErrorKind> {
+        self.get_as(name, "Path", |var| match var { CLPVar::Path(v) => Some(v.as_path()), _ => None })
+    }
+
+    pub fn get_path_or<'a>(&'a self, name: &str, default: &'a Path) -> Result<&'a Path, CLPErrorKind> {
+        or(self.get_path(name), default)
+    }
+
+    pub fn get_vec_int(&self, name: &str) -> Result<&[i128], CLPErrorKind> {
+        self.get_as(name, "VecInt", |var| match var { CLPVar::VecInt(v) => Some(v.as_slice()), _ => None })
+    }
+
+    pub fn get_vec_int_or<'a>(&'a self, name: &str, default: &'a [i128]) -> Result<&'a [i128], CLPErrorKind> {
+        or(self.get_vec_int(name), default)
+    }
+
+    pub fn get_vec_uint(&self, name: &str) -> Result<&[u128], CLPErrorKind> {
+        self.get_as(name, "VecUInt", |var| match var { CLPVar::VecUInt(v) => Some(v.as_slice()), _ => None })
+    }
+
+    pub fn get_vec_uint_or<'a>(&'a self, name: &str, default: &'a [u128]) -> Result<&'a [u128], CLPErrorKind> {
+        or(self.get_vec_uint(name), default)
+    }
+
+    pub fn get_vec_float(&self, name: &str) -> Result<&[f64], CLPErrorKind> {
+        self.get_as(name, "VecFloat", |var| match var { CLPVar::VecFloat(v) => Some(v.as_slice()), _ => None })
+    }
+
+    pub fn get_vec_float_or<'a>(&'a self, name: &str, default: &'a [f64]) -> Result<&'a [f64], CLPErrorKind> {
+        or(self.get_vec_float(name), default)
+    }
+
+    pub fn get_vec_str(&self, name: &str) -> Result<&[String], CLPErrorKind> {
+        self.get_as(name, "VecString", |var| match var { CLPVar::VecString(v) => Some(v.as_slice()), _ => None })
+    }
+
+    pub fn get_vec_str_or<'a>(&'a self, name: &str, default: &'a [String]) -> Result<&'a [String], CLPErrorKind> {
+        or(self.get_vec_str(name), default)
+    }
+
+    /// Whether the toggle `name` was given, `false` when it is missing.
+    pub fn flag(&self, name: &str) -> Result<bool, CLPErrorKind> {
+        self.count(name).map(|count| count > 0)
+    }
+
+    /// How often the toggle `name` was given, `0` when it is missing.
+    pub fn count(&self, name: &str) -> Result<usize, CLPErrorKind> {
+        or(self.get_as(name, "Flag", |var| match var { CLPVar::Flag(v) => Some(*v), _ => None }), 0)
+    }
+
+    /// Moves the value of `name` out of the result.
+    pub fn take(&mut self, name: &str) -> Result<CLPVar, CLPErrorKind> {
+        self.args.remove(name).ok_or_else(|| CLPErrorKind::MissingError(format!("no value for {}", name)))
+    }
+
+    /// Moves the value of `name` out of the result if `take` accepts its type,
+    /// otherwise the value stays in place.
+    fn take_as<T>(&mut self, name: &str, expected: &str, take: impl Fn(CLPVar) -> Result<T, CLPVar>) -> Result<T, CLPErrorKind> {
+        take(self.take(name)?).map_err(|var| {
+            let err = wrong_type(name, expected, &var);
+            self.args.insert(name.to_string(), var);
+            err
+        })
+    }
+
+    pub fn take_string(&mut self, name: &str) -> Result<String, CLPErrorKind> {
+        self.take_as(name, "String", |var| match var { CLPVar::String(v) => Ok(v), var => Err(var) })
+    }
+
+    pub fn take_vec_int(&mut self, name: &str) -> Result<Vec<i128>, CLPErrorKind> {
+        self.take_as(name, "VecInt", |var| match var { CLPVar::VecInt(v) => Ok(v), var => Err(var) })
+    }
+
+    pub fn take_vec_uint(&mut self, name: &str) -> Result<Vec<u128>, CLPErrorKind> {
+        self.take_as(name, "VecUInt", |var| match var { CLPVar::VecUInt(v) => Ok(v), var => Err(var) })
+    }
+
+    pub fn take_vec_float(&mut self, name: &str) -> Result<Vec<f64>, CLPErrorKind> {
+        self.take_as(name, "VecFloat", |var| match var { CLPVar::VecFloat(v) => Ok(v), var => Err(var) })
+    }
+
+    pub fn take_vec_string(&mut self, name: &str) -> Result<Vec<String>, CLPErrorKind> {
+        self.take_as(name, "VecString", |var| match var { CLPVar::VecString(v) => Ok(v), var => Err(var) })
+    }
+}
+
+/// Provenance of the values, values from the environment, config files or
+/// defaults are added with `insert` or `fill`.
+impl CLPResult {
+    /// Where the value of `name` came from, `None` when it has no value.
+    pub fn source(&self, name: &str) -> Option<&CLPSource> {
+        self.args.get(name).and(self.sources.get(name))
+    }
+
+    /// Sets `name` to `var` from `source` and returns the value it replaces.
+    pub fn insert(&mut self, name: &str, var: CLPVar, source: CLPSource) -> Option<CLPVar> {
+        self.sources.insert(name.to_string(), source);
+        self.args.insert(name.to_string(), var)
+    }
+
+    /// Sets `name` to `var` from `source` unless it already has a value and
+    /// returns whether it did. Filling from the environment, then from a
+    /// config file and then with defaults gives each source precedence over
+    /// the ones after it, with the command line first.
+    pub fn fill(&mut self, name: &str, var: CLPVar, source: CLPSource) -> bool {
+        if self.args.contains_key(name) {
+            return false;
+        }
+        self.insert(name, var, source);
+        true
+    }
+}
+
+/// Order of the arguments on the command line.
+impl CLPResult {
+    /// The argv indices at which `name` was given, in order.
+    pub fn indices_of(&self, name: &str) -> Vec<usize> {
+        self.occurrences.iter()
+            .filter(|occurrence| occurrence.name == name)
+            .map(|occurrence| occurrence.index)
+            .collect()
+    }
+
+    /// Every positional and keyword option as given, in command line order.
+    pub fn occurrences_in_order(&self) -> &[CLPOccurrence] {
+        &self.occurrences
+    }
+}
@@ -0,0 +1,35 @@
+use super::numbers::scale;
+
+const UNITS: [(&str, u128); 14] = [
+    ("b", 1),
+    ("k", 1_000),
+    ("kb", 1_000),
+    ("kib", 1 << 10),
+    ("m", 1_000_000),
+    ("mb", 1_000_000),
+    ("mib", 1 << 20),
+    ("g", 1_000_000_000),
+    ("gb", 1_000_000_000),
+    ("gib", 1 << 30),
+    ("t", 1_000_000_000_000),
+    ("tb", 1_000_000_000_000),
+    ("tib", 1 << 40),
+    ("", 1),
+];
+
+/// Parses byte sizes like `4k`, `1.5G` or `512MiB`. Suffixes are case
+/// insensitive, SI suffixes are powers of 1000 and IEC suffixes powers of 1024.
+/// Fractions of a byte are dropped.
+pub(crate) fn parse_size(arg: &str) -> Result<u64, String> {
+    let number_end = arg.find(|char: char| !(char.is_ascii_digit() || char == '.')).unwrap_or(arg.len());
+    let (number, unit) = (&arg[..number_end], arg[number_end..].trim_start());
+    if number.is_empty() {
+        return Err(format!("expected a number got {:?}", arg));
+    }
+    let unit = match UNITS.iter().find(|(name, _)| name.eq_ignore_ascii_case(unit)) {
+        Some((_, unit)) => *unit,
+        None => return Err(format!("unknown unit {:?}, expected one of B, K, KB, KiB, M, MB, MiB, G, GB, GiB, T, TB, TiB", unit)),
+    };
+    let bytes = scale(number, unit)?;
+    u64::try_from(bytes).map_err(|_| format!("{} overflows a 64 bit byte count", arg))
+}
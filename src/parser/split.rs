@@ -0,0 +1,28 @@
+/// Splits `arg` on `separator`, ignoring separators inside quotes and
+/// brackets so nested literals stay in one piece.
+pub(crate) fn split_outside(arg: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut depth: usize = 0;
+    let mut start = 0;
+    let mut chars = arg.char_indices();
+    while let Some((id, char)) = chars.next() {
+        match (quote, char) {
+            (Some(_), '\\') => {
+                chars.next();
+            },
+            (Some(open), char) if char == open => quote = None,
+            (Some(_), _) => (),
+            (None, '\'') | (None, '"') => quote = Some(char),
+            (None, '[') => depth += 1,
+            (None, ']') => depth = depth.saturating_sub(1),
+            (None, char) if char == separator && depth == 0 => {
+                parts.push(&arg[start..id]);
+                start = id + char.len_utf8();
+            },
+            _ => (),
+        }
+    }
+    parts.push(&arg[start..]);
+    parts
+}
@@ -0,0 +1,47 @@
+fn unescape(char: char, chars: &mut std::str::Chars) -> Result<char, String> {
+    match char {
+        'n' => Ok('\n'),
+        't' => Ok('\t'),
+        'r' => Ok('\r'),
+        '0' => Ok('\0'),
+        '\\' | '\'' | '"' => Ok(char),
+        'u' => {
+            if chars.next() != Some('{') {
+                return Err("expected { after \\u".to_string());
+            }
+            let hex: String = chars.by_ref().take_while(|char| *char != '}').collect();
+            u32::from_str_radix(&hex, 16).ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| format!("invalid unicode escape \\u{{{}}}", hex))
+        },
+        char => Err(format!("unknown escape \\{}", char)),
+    }
+}
+
+/// Parses one string item: `'quoted'` or `"quoted"` with backslash escapes
+/// (`\n`, `\t`, `\r`, `\0`, `\\`, `\'`, `\"` and `\u{..}`), or a bare word
+/// that is taken as is.
+pub(crate) fn parse_item(item: &str) -> Result<String, String> {
+    let mut chars = item.chars();
+    let quote = match chars.next() {
+        Some(quote) if quote == '\'' || quote == '"' => quote,
+        _ => return Ok(item.to_string()),
+    };
+    let mut string = String::new();
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' => match chars.next() {
+                Some(char) => string.push(unescape(char, &mut chars)?),
+                None => break,
+            },
+            char if char == quote => {
+                return match chars.as_str() {
+                    "" => Ok(string),
+                    rest => Err(format!("unexpected {:?} after closing {}", rest, quote)),
+                };
+            },
+            char => string.push(char),
+        }
+    }
+    Err(format!("expected {} got End Of Argument", quote))
+}
@@ -0,0 +1,49 @@
+/// Edits to turn `a` into `b`: inserting, removing or replacing a character
+/// and swapping two adjacent ones, so `--verbsoe` is one edit from `--verbose`.
+pub(crate) fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // rows[i][j] is the distance between a[..i] and b[..j]
+    let mut rows = vec![(0..=b.len()).collect::<Vec<usize>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            row[j] = (rows[i - 1][j] + 1).min(row[j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+/// The candidates closest to `word` if they are at most `max` edits away,
+/// sorted.
+pub(crate) fn closest<'a>(word: &str, candidates: impl Iterator<Item = &'a str>, max: usize) -> Vec<&'a str> {
+    let mut best = Vec::new();
+    let mut best_distance = max + 1;
+    for candidate in candidates {
+        let distance = distance(word, candidate);
+        if distance < best_distance {
+            best_distance = distance;
+            best.clear();
+        }
+        if distance == best_distance {
+            best.push(candidate);
+        }
+    }
+    best.sort();
+    best
+}
+
+/// `, did you mean `a`?` or `, did you mean `a` or `b`?`, empty without
+/// suggestions.
+pub(crate) fn did_you_mean(suggestions: &[&str]) -> String {
+    match suggestions {
+        [] => String::new(),
+        [suggestion] => format!(", did you mean `{}`?", suggestion),
+        [rest @ .., last] => format!(", did you mean {} or `{}`?", rest.iter().map(|suggestion| format!("`{}`", suggestion)).collect::<Vec<_>>().join(", "), last),
+    }
+}
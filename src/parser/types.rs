@@ -1,4 +1,8 @@
 use std::collections::HashMap;
+use std::ffi::OsString;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
 
 
 //arguments
@@ -10,7 +14,32 @@ pub enum ArgTypes {
     VECINT,
     VECUINT,
     VECFLOAT,
-    VECSTRING
+    VECSTRING,
+    VECVECINT,
+    VECVECUINT,
+    VECVECFLOAT,
+    DURATION,
+    SIZE,
+    IPV4,
+    IPV6,
+    IPADDR,
+    SOCKETADDR,
+    VECIPADDR,
+    VECSOCKETADDR,
+    MAP(Box<ArgTypes>),
+    TUPLE(Vec<ArgTypes>, CLPTupleStyle),
+    DATE,
+    DATETIME,
+    RELTIME,
+    PATH,
+    OSSTRING
+}
+
+/// How the elements of a `TUPLE` are written: `[10, 'label', 0.5]` or joined
+/// by a separator as in `1920x1080`.
+pub enum CLPTupleStyle {
+    Bracketed,
+    Separated(char)
 }
 
 pub enum ArgsSettings {
@@ -30,6 +59,42 @@ impl std::fmt::Debug for ArgTypes {
             ArgTypes::VECUINT => write!(f, "[...UINT...]"),
             ArgTypes::VECFLOAT => write!(f, "[...FLOAT...]"),
             ArgTypes::VECSTRING => write!(f, "[...STRING...]"),
+            ArgTypes::VECVECINT => write!(f, "[[...INT...]]"),
+            ArgTypes::VECVECUINT => write!(f, "[[...UINT...]]"),
+            ArgTypes::VECVECFLOAT => write!(f, "[[...FLOAT...]]"),
+            ArgTypes::DURATION => write!(f, "DURATION"),
+            ArgTypes::SIZE => write!(f, "SIZE"),
+            ArgTypes::IPV4 => write!(f, "IPV4"),
+            ArgTypes::IPV6 => write!(f, "IPV6"),
+            ArgTypes::IPADDR => write!(f, "IPADDR"),
+            ArgTypes::SOCKETADDR => write!(f, "SOCKETADDR"),
+            ArgTypes::VECIPADDR => write!(f, "[...IPADDR...]"),
+            ArgTypes::VECSOCKETADDR => write!(f, "[...SOCKETADDR...]"),
+            ArgTypes::MAP(value_type) => write!(f, "[...KEY={:?}...]", value_type),
+            ArgTypes::DATE => write!(f, "DATE"),
+            ArgTypes::DATETIME => write!(f, "DATETIME"),
+            ArgTypes::RELTIME => write!(f, "RELTIME"),
+            ArgTypes::PATH => write!(f, "PATH"),
+            ArgTypes::OSSTRING => write!(f, "OSSTRING"),
+            ArgTypes::TUPLE(types, CLPTupleStyle::Bracketed) => {
+                write!(f, "[")?;
+                for (index, element_type) in types.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}", element_type)?;
+                }
+                write!(f, "]")
+            },
+            ArgTypes::TUPLE(types, CLPTupleStyle::Separated(separator)) => {
+                for (index, element_type) in types.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, "{}", separator)?;
+                    }
+                    write!(f, "{:?}", element_type)?;
+                }
+                Ok(())
+            },
         }
     }
 }
@@ -39,16 +104,207 @@ pub struct KwargSettings {
     pub keyvalues: HashMap<String, KwargTypes>
 }
 
+/// Keyword options. `Important` options must be given, `Optional` ones may be
+/// left out and `Toggle` options take no value.
+///
+/// The strings are `[description, value hint, error message]`; a `{}` in the
+/// error message is replaced by the rejected value. The value of a `Toggle` is
+/// stored under the keyword without its leading dashes.
 pub enum KwargTypes {
-    Important([String; 3]),
-    Optional([String; 3]),
+    Important(CLPInput, [String; 3]),
+    Optional(CLPInput, [String; 3]),
     Toggle([String; 1])
 }
 
+/// How the elements of a vector are written. `Bracketed` takes `[1, 2, 3]`,
+/// `Bare` takes `1,2,3`, `Separator` splits on its character as in
+/// `/bin:/usr/bin` and `Whitespace` splits on runs of whitespace. Nested
+/// vectors are always bracketed.
+///
+/// Whitespace around elements is trimmed. `[]` and an empty bare list are
+/// empty vectors. A single trailing delimiter is ignored, any other empty
+/// element is rejected.
+#[derive(Debug)]
+pub enum CLPVecSyntax {
+    Bracketed,
+    Bare,
+    Separator(char),
+    Whitespace
+}
+
+/// Range list mode for `VECINT` and `VECUINT`. Elements may be ranges written
+/// `a-b` or `a..=b` (inclusive) or `a..b` (exclusive), optionally followed by
+/// a step as in `0..100:10`. Reversed ranges count down unless rejected.
+/// `reject_overlap` rejects a value given by two elements, so `0..10:5,3` is
+/// accepted. A list expanding to more than 1048576 values is an error.
+#[derive(Debug, Default)]
+pub struct CLPRanges {
+    pub reject_reversed: bool,
+    pub reject_overlap: bool,
+    pub dedup_sort: bool
+}
+
+/// What to do when a `MAP` input sees the same key twice.
+#[derive(Debug)]
+pub enum CLPDuplicate {
+    LastWins,
+    FirstWins,
+    Error
+}
+
+//constraints
+/// Value constraints checked after an argument has been parsed. Range bounds
+/// are inclusive and `None` leaves that side open. For vector types every
+/// element is checked.
+///
+/// `Shape(rows, columns)` and `Rectangular` apply to nested vectors, `Shape`
+/// only checks the dimensions that are given.
+///
+/// `Length(min, max)` bounds the number of elements of a vector or map,
+/// `Unique`, `Ascending` and `Descending` check the elements of a vector. A
+/// map collected from repeated options is checked with all its pairs.
+pub enum CLPConstraint {
+    IntRange(Option<i128>, Option<i128>),
+    UIntRange(Option<u128>, Option<u128>),
+    FloatRange(Option<f64>, Option<f64>),
+    Finite,
+    Shape(Option<usize>, Option<usize>),
+    Rectangular,
+    Length(Option<usize>, Option<usize>),
+    Unique,
+    Ascending,
+    Descending,
+    Custom(fn(&CLPVar) -> Result<(), String>)
+}
+
+impl std::fmt::Debug for CLPConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fn bound<T: std::fmt::Debug>(f: &mut std::fmt::Formatter, min: &Option<T>, max: &Option<T>) -> std::fmt::Result {
+            match min {
+                Some(min) => write!(f, "[{:?}, ", min)?,
+                None => write!(f, "(-inf, ")?,
+            }
+            match max {
+                Some(max) => write!(f, "{:?}]", max),
+                None => write!(f, "+inf)"),
+            }
+        }
+        match self {
+            CLPConstraint::IntRange(min, max) => bound(f, min, max),
+            CLPConstraint::UIntRange(min, max) => bound(f, min, max),
+            CLPConstraint::FloatRange(min, max) => bound(f, min, max),
+            CLPConstraint::Finite => write!(f, "finite"),
+            CLPConstraint::Shape(rows, columns) => {
+                let dimension = |size: &Option<usize>| size.map_or("_".to_string(), |size| size.to_string());
+                write!(f, "shape {}x{}", dimension(rows), dimension(columns))
+            },
+            CLPConstraint::Rectangular => write!(f, "rectangular"),
+            CLPConstraint::Length(min, max) => bound(f, min, max),
+            CLPConstraint::Unique => write!(f, "unique"),
+            CLPConstraint::Ascending => write!(f, "ascending"),
+            CLPConstraint::Descending => write!(f, "descending"),
+            CLPConstraint::Custom(_) => write!(f, "custom"),
+        }
+    }
+}
+
 //Result and input
+/// A typed input. Integers accept `0x`, `0o` and `0b` prefixes, `_` digit
+/// separators and a leading `+` unless `decimal_only` is set.
+///
+/// Durations take a number followed by one of `ns`, `us`, `ms`, `s`, `m`, `h`
+/// or `d`, repeated as in `1h30m`. A bare number is read in `duration_unit`
+/// and rejected when it is `None`.
+///
+/// Socket addresses given without a port use `default_port`, or are rejected
+/// when it is `None`.
+///
+/// `set` removes repeated elements from vectors, keeping the first one.
+///
+/// String vectors take single or double quoted items with backslash escapes
+/// or bare words, as in `['/usr/bin', "a\tb", c]`. `legacy_escape` switches
+/// back to single quoted items with `/` as the escape character.
+///
+/// Maps take `key=value` or `[key=value, ...]` and collect the pairs of
+/// repeated keyword options, `duplicate_keys` decides which value a repeated
+/// key keeps. Keys and string values may be quoted like the items of a string
+/// vector, as in `[name='a, b', "x=y"=1]`, and a single trailing comma is
+/// ignored.
 pub struct CLPInput {
     pub object_type: ArgTypes,
-    pub name: String
+    pub name: String,
+    pub constraints: Vec<CLPConstraint>,
+    pub decimal_only: bool,
+    pub duration_unit: Option<Duration>,
+    pub default_port: Option<u16>,
+    pub duplicate_keys: CLPDuplicate,
+    pub vec_syntax: CLPVecSyntax,
+    pub ranges: Option<CLPRanges>,
+    pub legacy_escape: bool,
+    pub set: bool
+}
+
+impl CLPInput {
+    pub fn new(object_type: ArgTypes, name: &str) -> CLPInput {
+        CLPInput {
+            object_type,
+            name: name.to_string(),
+            constraints: Vec::new(),
+            decimal_only: false,
+            duration_unit: None,
+            default_port: None,
+            duplicate_keys: CLPDuplicate::LastWins,
+            vec_syntax: CLPVecSyntax::Bracketed,
+            ranges: None,
+            legacy_escape: false,
+            set: false
+        }
+    }
+
+    pub fn constraint(mut self, constraint: CLPConstraint) -> CLPInput {
+        self.constraints.push(constraint);
+        self
+    }
+
+    pub fn decimal_only(mut self) -> CLPInput {
+        self.decimal_only = true;
+        self
+    }
+
+    pub fn duration_unit(mut self, unit: Duration) -> CLPInput {
+        self.duration_unit = Some(unit);
+        self
+    }
+
+    pub fn default_port(mut self, port: u16) -> CLPInput {
+        self.default_port = Some(port);
+        self
+    }
+
+    pub fn duplicate_keys(mut self, duplicate_keys: CLPDuplicate) -> CLPInput {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
+
+    pub fn vec_syntax(mut self, vec_syntax: CLPVecSyntax) -> CLPInput {
+        self.vec_syntax = vec_syntax;
+        self
+    }
+
+    pub fn ranges(mut self, ranges: CLPRanges) -> CLPInput {
+        self.ranges = Some(ranges);
+        self
+    }
+
+    pub fn legacy_escape(mut self) -> CLPInput {
+        self.legacy_escape = true;
+        self
+    }
+
+    pub fn set(mut self) -> CLPInput {
+        self.set = true;
+        self
+    }
 }
 
 impl std::fmt::Debug for CLPInput {
@@ -57,7 +313,55 @@ impl std::fmt::Debug for CLPInput {
     }
 }
 
-#[derive(Debug)]
+/// A calendar date as parsed from `YYYY-MM-DD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CLPDate {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8
+}
+
+/// A date and time. `offset` is the UTC offset in seconds, `None` if the input
+/// had none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CLPDateTime {
+    pub date: CLPDate,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub nanosecond: u32,
+    pub offset: Option<i32>
+}
+
+impl CLPDateTime {
+    /// Seconds since the unix epoch, a missing offset is taken as UTC.
+    pub fn timestamp(&self) -> i64 {
+        let days = super::dates::days_from_civil(self.date.year as i64, self.date.month as u32, self.date.day as u32);
+        days * 86400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64 - self.offset.unwrap_or(0) as i64
+    }
+}
+
+impl std::fmt::Display for CLPDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl std::fmt::Display for CLPDateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}T{:02}:{:02}:{:02}", self.date, self.hour, self.minute, self.second)?;
+        if self.nanosecond != 0 {
+            write!(f, ".{}", format!("{:09}", self.nanosecond).trim_end_matches('0'))?;
+        }
+        match self.offset {
+            Some(0) => write!(f, "Z"),
+            Some(offset) => write!(f, "{}{:02}:{:02}", if offset < 0 { '-' } else { '+' }, offset.abs() / 3600, offset.abs() / 60 % 60),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum CLPVar {
     Int(i128),
     UInt(u128),
@@ -66,14 +370,73 @@ pub enum CLPVar {
     VecInt(Vec<i128>),
     VecUInt(Vec<u128>),
     VecFloat(Vec<f64>),
-    VecString(Vec<String>)
+    VecString(Vec<String>),
+    VecVecInt(Vec<Vec<i128>>),
+    VecVecUInt(Vec<Vec<u128>>),
+    VecVecFloat(Vec<Vec<f64>>),
+    Duration(Duration),
+    Size(u64),
+    IpAddr(IpAddr),
+    SocketAddr(SocketAddr),
+    VecIpAddr(Vec<IpAddr>),
+    VecSocketAddr(Vec<SocketAddr>),
+    Map(HashMap<String, CLPVar>),
+    Tuple(Vec<CLPVar>),
+    Date(CLPDate),
+    DateTime(CLPDateTime),
+    Path(PathBuf),
+    OsString(OsString),
+    Flag(usize)
 }
 
-#[derive(Debug)]
+/// Where a value of a `CLPResult` came from. `CommandLine` holds the argv
+/// index of the argument, `arg0` being 0, for a keyword option the index of
+/// its key. `Config` holds the path and line of a config file.
+///
+/// `parse` only records `CommandLine`. `Default` is recorded by
+/// `CLPArgs::parse` for the `default = ".."` of a derived struct, values from
+/// the environment or config files are added by the caller with
+/// `CLPResult::insert` or `CLPResult::fill`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CLPSource {
+    CommandLine(usize),
+    Default,
+    Env(String),
+    Config(PathBuf, usize)
+}
+
+impl std::fmt::Display for CLPSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CLPSource::CommandLine(index) => write!(f, "command line argument {}", index),
+            CLPSource::Default => write!(f, "default"),
+            CLPSource::Env(name) => write!(f, "environment variable {}", name),
+            CLPSource::Config(path, line) => write!(f, "{} line {}", path.display(), line),
+        }
+    }
+}
+
+/// One positional or keyword option on the command line. `index` is its argv
+/// index, `key` the key of a keyword option as written and `value` the value
+/// of this occurrence alone, repeated options keep only the last value or
+/// the merged map in `CLPResult::args`. A toggle has the value `Flag(1)`.
+#[derive(Debug, Clone)]
+pub struct CLPOccurrence {
+    pub name: String,
+    pub key: Option<String>,
+    pub index: usize,
+    pub value: CLPVar
+}
+
+/// `sources` holds the `CLPSource` of every entry in `args` and `occurrences`
+/// every positional and keyword option in command line order.
+#[derive(Debug, Default)]
 pub struct CLPResult {
     pub arg0: String,
     pub default: Vec<String>,
-    pub args: HashMap<String, CLPVar>
+    pub args: HashMap<String, CLPVar>,
+    pub sources: HashMap<String, CLPSource>,
+    pub occurrences: Vec<CLPOccurrence>
 }
 
 //Error Kind
@@ -82,5 +445,169 @@ pub enum CLPErrorKind {
     Error(String),
     ParseError(String),
     KwargError(String),
-    ArgsError(String)
-}
\ No newline at end of file
+    ArgsError(String),
+    ConstraintError(String),
+    MissingError(String),
+    TypeError(String)
+}
+
+impl CLPErrorKind {
+    pub fn message(&self) -> &str {
+        match self {
+            CLPErrorKind::Error(message)
+            | CLPErrorKind::ParseError(message)
+            | CLPErrorKind::KwargError(message)
+            | CLPErrorKind::ArgsError(message)
+            | CLPErrorKind::ConstraintError(message)
+            | CLPErrorKind::MissingError(message)
+            | CLPErrorKind::TypeError(message) => message,
+        }
+    }
+}
+
+impl std::fmt::Display for CLPErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for CLPErrorKind {}
+
+/// What went wrong, see `CLPError`. `MisplacedOption` is a keyword option
+/// given before all positionals. `TypeMismatch` is never reported by `parse`,
+/// only by converting a parsed value to another type, as `CLPArgs::parse`
+/// does when a `CLPErrorKind::TypeError` of `parse_from` becomes a `CLPError`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CLPErrorReason {
+    UnknownOption,
+    UnexpectedArgument,
+    MisplacedOption,
+    MissingValue,
+    MissingArgument,
+    MissingOption,
+    InvalidValue,
+    ConstraintViolation,
+    TypeMismatch,
+    Other
+}
+
+/// An error of `CommandLineParser::parse` with its context. `arg` is the name
+/// of the positional or the key of the keyword option, `token` the offending
+/// argument, `index` its argv index and `expected` the expected type.
+#[derive(Debug)]
+pub struct CLPError {
+    pub reason: CLPErrorReason,
+    pub message: String,
+    pub arg: Option<String>,
+    pub token: Option<String>,
+    pub index: Option<usize>,
+    pub expected: Option<String>
+}
+
+impl CLPError {
+    /// An error for `reason` with the message of `error`.
+    pub fn new(reason: CLPErrorReason, error: CLPErrorKind) -> CLPError {
+        CLPError {
+            reason,
+            message: error.message().to_string(),
+            arg: None,
+            token: None,
+            index: None,
+            expected: None
+        }
+    }
+
+    pub fn arg(mut self, arg: &str) -> CLPError {
+        self.arg = Some(arg.to_string());
+        self
+    }
+
+    pub fn token(mut self, token: &std::ffi::OsStr) -> CLPError {
+        self.token = Some(token.to_string_lossy().into_owned());
+        self
+    }
+
+    pub fn index(mut self, index: usize) -> CLPError {
+        self.index = Some(index);
+        self
+    }
+
+    pub fn expected(mut self, expected: &ArgTypes) -> CLPError {
+        self.expected = Some(format!("{:?}", expected));
+        self
+    }
+
+    /// The exit code to report the error with, 2 for a misuse of the command
+    /// line as is common for usage errors, 70 for a value read with the wrong
+    /// type (`EX_SOFTWARE`), which `parse` never reports, and 1 otherwise.
+    pub fn exit_code(&self) -> i32 {
+        match self.reason {
+            CLPErrorReason::TypeMismatch => 70,
+            CLPErrorReason::Other => 1,
+            _ => 2,
+        }
+    }
+}
+
+impl From<CLPErrorKind> for CLPError {
+    fn from(error: CLPErrorKind) -> CLPError {
+        let reason = match error {
+            CLPErrorKind::Error(_) => CLPErrorReason::Other,
+            CLPErrorKind::ParseError(_) | CLPErrorKind::KwargError(_) | CLPErrorKind::ArgsError(_) => CLPErrorReason::InvalidValue,
+            CLPErrorKind::ConstraintError(_) => CLPErrorReason::ConstraintViolation,
+            CLPErrorKind::MissingError(_) => CLPErrorReason::MissingArgument,
+            CLPErrorKind::TypeError(_) => CLPErrorReason::TypeMismatch,
+        };
+        CLPError::new(reason, error)
+    }
+}
+
+impl From<CLPError> for CLPErrorKind {
+    fn from(error: CLPError) -> CLPErrorKind {
+        match error.reason {
+            CLPErrorReason::UnknownOption | CLPErrorReason::UnexpectedArgument | CLPErrorReason::Other => CLPErrorKind::Error(error.message),
+            CLPErrorReason::MisplacedOption | CLPErrorReason::MissingArgument => CLPErrorKind::ArgsError(error.message),
+            CLPErrorReason::MissingValue | CLPErrorReason::MissingOption => CLPErrorKind::KwargError(error.message),
+            CLPErrorReason::InvalidValue => CLPErrorKind::ParseError(error.message),
+            CLPErrorReason::ConstraintViolation => CLPErrorKind::ConstraintError(error.message),
+            CLPErrorReason::TypeMismatch => CLPErrorKind::TypeError(error.message),
+        }
+    }
+}
+
+impl std::fmt::Display for CLPError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CLPError {}
+
+/// Every error of `CommandLineParser::parse_all` in argv order, errors
+/// without an index come last. `result` holds the values that did parse.
+#[derive(Debug)]
+pub struct CLPErrors {
+    pub errors: Vec<CLPError>,
+    pub result: Box<CLPResult>
+}
+
+impl CLPErrors {
+    /// The exit code of the first error.
+    pub fn exit_code(&self) -> i32 {
+        self.errors.first().map_or(1, CLPError::exit_code)
+    }
+}
+
+impl std::fmt::Display for CLPErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (index, error) in self.errors.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CLPErrors {}